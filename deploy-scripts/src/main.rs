@@ -26,7 +26,7 @@ use ccd_multisig::{
     ApproveParameter,
     TxParameter
 }; // Example
-use crate::contracts_common::{AccountAddress,Address};
+use crate::contracts_common::{AccountAddress,Address,Duration};
 
 
 /// Reads the wasm module from a given file path.
@@ -99,7 +99,16 @@ async fn main() -> Result<(), Error> {
     admins.push(Address::from_str("36J5gb5QVYBvbda4cZkagN4LvVCXejyX8ScuEx8xyAQckVjBMA").unwrap());
 
 
-    let init_params = InitParameter{admins};
+    // Any two of the three admins can approve a transfer.
+    let init_params = InitParameter{
+        admins,
+        weights: Vec::new(),
+        threshold: 2,
+        roles: Vec::new(),
+        max_lifetime: Duration::from_days(30),
+        execution_delay: Duration::from_hours(24),
+        auto_execute: false,
+    };
 
 
     let param: OwnedParameter = OwnedParameter::from_serial(&init_params).unwrap(); // Example
@@ -124,7 +133,7 @@ async fn main() -> Result<(), Error> {
 
     let receiver = AccountAddress::from_str("3UsPQ4MxhGNLEbYac53H7C2JHzE3Xe41zrgCdLVrp5vphx4YSe").unwrap();
 
-    let input_parameter  = TxParameter::new(0,receiver,10000); // Example
+    let input_parameter  = TxParameter::new(receiver,10000); // Example
 
     // Create a successful transaction.
 
@@ -2,15 +2,62 @@
 
 //! # A Concordium V1 smart contract
 use concordium_std::*;
+use concordium_cis2::{
+    AdditionalData, OnReceivingCis2Params, Receiver, TokenAmountU256, TokenIdVec, Transfer,
+    TransferParams,
+};
 use core::fmt::Debug;
 
+/// Version of the state layout this module uses, see `migrate`.
+///
+/// 1. The layout `Upgrade` proposals were introduced with.
+/// 2. Adds `State::paused`.
+/// 3. Adds the deposit ledger.
+/// 4. Adds `State::committed` and `Proposal::reserved`.
+pub const STATE_VERSION: u32 = 4;
 
 
 #[derive(Debug, Serial, DeserialWithState)]
 #[concordium(state_parameter = "S")]
 pub struct State<S: HasStateApi = StateApi> {
+    /// Layout version of the state, bumped by migrations after an upgrade.
+    pub version: u32,
     pub transactions: StateMap<u32,Proposal,S>,
     pub admins: StateBox<Vec<Address>,S>,
+    /// Voting weight of every admin.
+    pub weights: StateMap<Address,u8,S>,
+    /// Roles granted on top of those the admins hold, see [`Role`].
+    pub roles: StateMap<Address,Vec<Role>,S>,
+    /// Approval weight a proposal needs before it can be executed.
+    pub threshold: u8,
+    /// Longest time a proposal stays open after it is created.
+    pub max_lifetime: Duration,
+    /// Time an approved proposal must wait before it can be executed.
+    pub execution_delay: Duration,
+    /// Whether every proposal is executed as soon as its final approval lands.
+    pub auto_execute: bool,
+    /// Set by `pause`, blocks everything but unpausing until an `Unpause`
+    /// proposal is executed.
+    pub paused: bool,
+    /// Index assigned to the next proposal created.
+    pub next_proposal_id: u32,
+    /// CIS-2 tokens held by the contract, per token contract and token id.
+    pub token_balances: StateMap<(ContractAddress, TokenIdVec),TokenAmountU256,S>,
+    /// Nonce the next permit of each signer must carry.
+    pub nonces: StateMap<AccountAddress,u64,S>,
+    /// CCD admins may spend without a proposal, see `spend`.
+    pub allowances: StateMap<Address,Allowance,S>,
+    /// Every CCD deposit made through `insert`, by sequence number.
+    pub deposits: StateMap<u64,Deposit,S>,
+    /// Number of deposits in the ledger.
+    pub deposit_count: u64,
+    /// CCD deposited per depositor.
+    pub deposit_totals: StateMap<Address,Amount,S>,
+    /// CCD deposited by everyone.
+    pub total_deposits: Amount,
+    /// CCD reserved for approved proposals, never more than the balance
+    /// when the reservation is made.
+    pub committed: Amount,
 }
 
 impl State {
@@ -20,6 +67,208 @@ impl State {
     pub fn is_owner(&self, sender: &Address) -> bool {
         self.admins.contains(sender) 
      }
+    /// Voting weight of an admin.
+    pub fn weight(&self, admin: &Address) -> u8 {
+        self.weights.get(admin).map(|weight| *weight).unwrap_or(1)
+    }
+    /// Every admin together with its voting weight.
+    pub fn signers(&self) -> Vec<(Address, u8)> {
+        self.admins.iter().map(|admin| (*admin, self.weight(admin))).collect()
+    }
+    /// Combined voting weight of all admins.
+    pub fn total_weight(&self) -> u32 {
+        self.admins.iter().map(|admin| self.weight(admin) as u32).sum()
+    }
+    /// Admins and addresses granted the `Proposer` role may create proposals.
+    pub fn can_propose(&self, sender: &Address) -> bool {
+        self.has_role(sender, Role::Proposer)
+    }
+    /// Whether `address` was granted `role`, without the roles admins hold.
+    pub fn is_granted(&self, address: &Address, role: Role) -> bool {
+        self.roles.get(address).map(|roles| roles.contains(&role)).unwrap_or(false)
+    }
+    /// Admins are the approvers and may also propose and execute, other
+    /// addresses hold the roles granted to them.
+    pub fn has_role(&self, address: &Address, role: Role) -> bool {
+        match role {
+            Role::Approver => self.is_owner(address),
+            Role::Proposer | Role::Executor => self.is_owner(address) || self.is_granted(address, role),
+            Role::Guardian => self.is_granted(address, role),
+        }
+    }
+    /// Every role `address` holds.
+    pub fn roles_of(&self, address: &Address) -> Vec<Role> {
+        [Role::Proposer, Role::Approver, Role::Executor, Role::Guardian]
+            .into_iter()
+            .filter(|role| self.has_role(address, *role))
+            .collect()
+    }
+
+    /// The part of `balance` not committed to approved proposals.
+    pub fn available(&self, balance: Amount) -> Amount {
+        Amount::from_micro_ccd(balance.micro_ccd.saturating_sub(self.committed.micro_ccd))
+    }
+
+    /// Reserves the CCD an approved proposal sends, failing with
+    /// `Overcommitted` if `balance` cannot cover it on top of what is
+    /// already committed. Pending and already reserved proposals are skipped.
+    pub fn reserve(&mut self, index: u32, balance: Amount) -> Result<(), Error> {
+        let mut proposal = self.transactions.get_mut(&index).ok_or(Error::ProposalNotFound)?;
+        if proposal.status != ProposalStatus::Approved || proposal.reserved > Amount::zero() {
+            return Ok(())
+        }
+        let outflow = proposal.kind.outflow().ok_or(Error::InvalidBatch)?;
+        let committed = self.committed.checked_add(outflow).ok_or(Error::Overcommitted)?;
+        ensure!(committed <= balance, Error::Overcommitted);
+        proposal.reserved = outflow;
+        self.committed = committed;
+        Ok(())
+    }
+
+    /// Returns the CCD reserved for a proposal to the available balance.
+    pub fn release(&mut self, index: u32) -> Result<(), Error> {
+        let mut proposal = self.transactions.get_mut(&index).ok_or(Error::ProposalNotFound)?;
+        let reserved = proposal.reserved;
+        proposal.reserved = Amount::zero();
+        self.committed = Amount::from_micro_ccd(self.committed.micro_ccd.saturating_sub(reserved.micro_ccd));
+        Ok(())
+    }
+
+    /// Fails while the contract is paused, unless `kind` is `Unpause` or
+    /// takes a key out of office, so a leaked key that keeps pausing can be
+    /// rotated out while paused.
+    pub fn ensure_active(&self, kind: &ProposalKind) -> Result<(), Error> {
+        let allowed = matches!(
+            kind,
+            ProposalKind::Unpause
+                | ProposalKind::RemoveSigner(_)
+                | ProposalKind::ReplaceSigner { .. }
+                | ProposalKind::RevokeRole { .. }
+        );
+        ensure!(!self.paused || allowed, Error::Paused);
+        Ok(())
+    }
+
+    /// Checks that `kind` is well formed and that executing it keeps the
+    /// threshold between 1 and the total weight, which must fit in a `u8`.
+    pub fn check_proposal(&self, kind: &ProposalKind) -> Result<(), Error> {
+        let total = self.total_weight();
+        let threshold = self.threshold as u32;
+        match kind {
+            ProposalKind::Transfer { .. }
+            | ProposalKind::ContractCall { .. }
+            | ProposalKind::TokenTransfer { .. }
+            | ProposalKind::Upgrade { .. } => {}
+            ProposalKind::BatchTransfer(legs) => {
+                ensure!(!legs.is_empty() && kind.outflow().is_some(), Error::InvalidBatch);
+            }
+            ProposalKind::AddSigner(signer) => {
+                ensure!(!self.is_owner(signer), Error::SignerAlreadyExists);
+                ensure!(total < u8::MAX as u32, Error::InvalidWeight);
+            }
+            ProposalKind::RemoveSigner(signer) => {
+                ensure!(self.is_owner(signer), Error::SignerNotFound);
+                ensure!(total - self.weight(signer) as u32 >= threshold, Error::InvalidThreshold);
+            }
+            ProposalKind::ReplaceSigner { old, new } => {
+                ensure!(self.is_owner(old), Error::SignerNotFound);
+                ensure!(!self.is_owner(new), Error::SignerAlreadyExists);
+            }
+            ProposalKind::ChangeWeight { signer, weight } => {
+                ensure!(self.is_owner(signer), Error::SignerNotFound);
+                let total = total - self.weight(signer) as u32 + *weight as u32;
+                ensure!(*weight > 0 && total <= u8::MAX as u32, Error::InvalidWeight);
+                ensure!(total >= threshold, Error::InvalidThreshold);
+            }
+            ProposalKind::ChangeThreshold(threshold) => {
+                ensure!(*threshold > 0 && *threshold as u32 <= total, Error::InvalidThreshold);
+            }
+            ProposalKind::GrantRole { address, role } => {
+                ensure!(*role != Role::Approver && !self.is_granted(address, *role), Error::InvalidRole);
+            }
+            ProposalKind::RevokeRole { address, role } => {
+                ensure!(self.is_granted(address, *role), Error::InvalidRole);
+            }
+            ProposalKind::SetAllowance { signer, limit, window } => {
+                ensure!(self.is_owner(signer), Error::SignerNotFound);
+                ensure!(*limit == Amount::zero() || window.millis() > 0, Error::InvalidAllowance);
+            }
+            ProposalKind::Unpause => ensure!(self.paused, Error::NotPaused),
+        }
+        Ok(())
+    }
+
+    /// Applies an approved governance proposal to the admins, their weights,
+    /// allowances, roles and the threshold. New admins weigh 1, a replacement
+    /// keeps the old weight, allowance and granted roles, a removed admin
+    /// loses its granted roles.
+    pub fn apply_governance(&mut self, kind: &ProposalKind) -> Result<(), Error> {
+        self.check_proposal(kind)?;
+        match kind {
+            ProposalKind::Transfer { .. }
+            | ProposalKind::BatchTransfer(_)
+            | ProposalKind::ContractCall { .. }
+            | ProposalKind::TokenTransfer { .. }
+            | ProposalKind::Upgrade { .. } => {}
+            ProposalKind::AddSigner(signer) => {
+                self.admins.push(*signer);
+                self.weights.insert(*signer, 1);
+            }
+            ProposalKind::RemoveSigner(signer) => {
+                self.admins.retain(|admin| admin != signer);
+                self.weights.remove(signer);
+                self.allowances.remove(signer);
+                self.roles.remove(signer);
+            }
+            ProposalKind::ReplaceSigner { old, new } => {
+                let weight = self.weight(old);
+                for admin in self.admins.iter_mut().filter(|admin| *admin == old) {
+                    *admin = *new;
+                }
+                self.weights.remove(old);
+                self.weights.insert(*new, weight);
+                if let Some(allowance) = self.allowances.remove_and_get(old) {
+                    self.allowances.insert(*new, allowance);
+                }
+                if let Some(granted) = self.roles.remove_and_get(old) {
+                    let mut roles = self.roles.get(new).map(|roles| roles.clone()).unwrap_or_default();
+                    for role in granted {
+                        if !roles.contains(&role) {
+                            roles.push(role);
+                        }
+                    }
+                    self.roles.insert(*new, roles);
+                }
+            }
+            ProposalKind::ChangeWeight { signer, weight } => {
+                self.weights.insert(*signer, *weight);
+            }
+            ProposalKind::ChangeThreshold(threshold) => self.threshold = *threshold,
+            ProposalKind::GrantRole { address, role } => {
+                let mut roles = self.roles.get(address).map(|roles| roles.clone()).unwrap_or_default();
+                roles.push(*role);
+                self.roles.insert(*address, roles);
+            }
+            ProposalKind::RevokeRole { address, role } => {
+                let mut roles = self.roles.get(address).map(|roles| roles.clone()).unwrap_or_default();
+                roles.retain(|granted| granted != role);
+                if roles.is_empty() {
+                    self.roles.remove(address);
+                } else {
+                    self.roles.insert(*address, roles);
+                }
+            }
+            ProposalKind::SetAllowance { signer, limit, window } => {
+                if *limit == Amount::zero() {
+                    self.allowances.remove(signer);
+                } else {
+                    self.allowances.insert(*signer, Allowance::new(*limit, *window));
+                }
+            }
+            ProposalKind::Unpause => self.paused = false,
+        }
+        Ok(())
+    }
 }
 
 pub trait IsOwner {
@@ -32,85 +281,661 @@ pub trait IsOwner {
     }
 }
 
-// proposal <amount, address>
+/// What an address may do. Admins are the approvers and may also propose
+/// and execute; the other roles can be granted to any address.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Role {
+    /// May create proposals.
+    Proposer,
+    /// May vote on proposals, i.e. is an admin.
+    Approver,
+    /// May execute approved proposals.
+    Executor,
+    /// May cancel any open proposal and pause the contract.
+    Guardian,
+}
+
+/// Where a proposal is in its lifecycle.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ProposalStatus {
+    /// Collecting approvals.
+    Pending,
+    /// Reached the threshold and can be executed.
+    Approved,
+    /// Executed successfully.
+    Executed,
+    /// Withdrawn before execution.
+    Cancelled,
+    /// Not executed before its deadline.
+    Expired,
+    /// Execution was attempted but the transfer did not go through.
+    Failed,
+}
+
+/// Events logged by the contract, one per state change.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A proposal was created.
+    ProposalCreated {
+        index: u32,
+        owner: Address,
+    },
+    /// A signer approved a proposal.
+    Approved {
+        index: u32,
+        signer: Address,
+    },
+    /// A signer withdrew their approval.
+    Revoked {
+        index: u32,
+        signer: Address,
+    },
+    /// A signer voted against a proposal.
+    Rejected {
+        index: u32,
+        signer: Address,
+    },
+    /// A proposal was executed successfully.
+    Executed {
+        index: u32,
+    },
+    /// Executing a proposal failed, it is now `Failed`.
+    ExecutionFailed {
+        index: u32,
+    },
+    /// A proposal was cancelled by its owner or by rejections.
+    Cancelled {
+        index: u32,
+    },
+    /// CCD was sent to the contract.
+    Deposit {
+        sender: Address,
+        amount: Amount,
+    },
+    /// CIS-2 tokens were sent to the contract.
+    TokenDeposit {
+        token: ContractAddress,
+        token_id: TokenIdVec,
+        amount: TokenAmountU256,
+        from: Address,
+    },
+    /// An address became an admin.
+    SignerAdded {
+        signer: Address,
+    },
+    /// An address stopped being an admin.
+    SignerRemoved {
+        signer: Address,
+    },
+    /// The approval weight proposals need changed.
+    ThresholdChanged {
+        threshold: u8,
+    },
+    /// The voting weight of an admin changed.
+    WeightChanged {
+        signer: Address,
+        weight: u8,
+    },
+    /// An address was granted a role.
+    RoleGranted {
+        address: Address,
+        role: Role,
+    },
+    /// A role was taken away from an address.
+    RoleRevoked {
+        address: Address,
+        role: Role,
+    },
+    /// The spending allowance of an admin changed.
+    AllowanceChanged {
+        signer: Address,
+        limit: Amount,
+        window: Duration,
+    },
+    /// A guardian or admin paused the contract.
+    Paused {
+        by: Address,
+    },
+    /// An `Unpause` proposal lifted the pause.
+    Unpaused,
+    /// The contract was upgraded to a new module.
+    Upgraded {
+        module: ModuleReference,
+    },
+    /// An admin spent CCD from their allowance.
+    Spent {
+        signer: Address,
+        receiver: AccountAddress,
+        amount: Amount,
+    },
+}
+
+/// CCD an admin may spend per window without a proposal, see `spend`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Allowance {
+    /// CCD the admin may spend per window.
+    pub limit: Amount,
+    /// Length of a window.
+    pub window: Duration,
+    /// When the current window started.
+    pub window_start: Timestamp,
+    /// CCD spent in the current window.
+    pub spent: Amount,
+}
+
+impl Allowance {
+    /// A fresh allowance, its first window starts with the first spend.
+    pub fn new(limit: Amount, window: Duration) -> Self {
+        Allowance { limit, window, window_start: Timestamp::from_timestamp_millis(0), spent: Amount::zero() }
+    }
+
+    /// The allowance at block time `now`, a new window starts once the
+    /// current one has passed.
+    pub fn at(self, now: Timestamp) -> Self {
+        match self.window_start.checked_add(self.window) {
+            Some(end) if now < end => self,
+            _ => Allowance { window_start: now, spent: Amount::zero(), ..self },
+        }
+    }
+
+    /// CCD that can still be spent in the current window.
+    pub fn remaining(&self) -> Amount {
+        self.limit.subtract_micro_ccd(self.spent.micro_ccd.min(self.limit.micro_ccd))
+    }
+}
+
+/// Longest memo, in bytes, a deposit may carry.
+pub const MAX_MEMO_LENGTH: usize = 256;
+
+/// A CCD deposit recorded by `insert`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq, Clone)]
+pub struct Deposit {
+    pub sender: Address,
+    pub amount: Amount,
+    /// Block time of the deposit.
+    pub time: Timestamp,
+    pub memo: Option<String>,
+}
+
+/// One payment of a batch transfer.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq, Clone)]
+pub struct TransferLeg {
+    pub receiver: AccountAddress,
+    pub amount: Amount,
+}
+
+/// Result of executing a `ContractCall` proposal.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq, Clone)]
+pub struct CallOutcome {
+    /// Whether the called contract accepted the call.
+    pub success: bool,
+    /// The bytes the called contract returned, if any.
+    pub return_value: Vec<u8>,
+}
+
+/// What a proposal does when it is executed.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq, Clone)]
+pub enum ProposalKind {
+    /// Send `amount` CCD from the contract to `receiver`.
+    Transfer {
+        receiver: AccountAddress,
+        amount: Amount,
+    },
+    /// Pay every leg at once, either all transfers go through or none.
+    BatchTransfer(Vec<TransferLeg>),
+    /// Call `entrypoint` of the contract at `address` with a serialized
+    /// `parameter`, sending `amount` CCD along.
+    ContractCall {
+        address: ContractAddress,
+        entrypoint: OwnedEntrypointName,
+        parameter: OwnedParameter,
+        amount: Amount,
+    },
+    /// Send `amount` of the CIS-2 token `token_id` held by the contract to
+    /// `receiver`, by calling `transfer` on the `token` contract.
+    TokenTransfer {
+        token: ContractAddress,
+        token_id: TokenIdVec,
+        amount: TokenAmountU256,
+        receiver: AccountAddress,
+    },
+    /// Upgrade the contract to `module`, then call the `migration`
+    /// entrypoint of the upgraded contract with its parameter, if any.
+    /// Executed by the `upgrade` entrypoint rather than `transfer`.
+    Upgrade {
+        module: ModuleReference,
+        migration: Option<(OwnedEntrypointName, OwnedParameter)>,
+    },
+    /// Make an address an admin.
+    AddSigner(Address),
+    /// Remove an admin.
+    RemoveSigner(Address),
+    /// Swap the admin `old` for `new`, e.g. to rotate a key.
+    ReplaceSigner {
+        old: Address,
+        new: Address,
+    },
+    /// Set the voting weight of an admin.
+    ChangeWeight {
+        signer: Address,
+        weight: u8,
+    },
+    /// Set the approval weight proposals need.
+    ChangeThreshold(u8),
+    /// Grant `role` to `address`. Approvers are added with `AddSigner` instead.
+    GrantRole {
+        address: Address,
+        role: Role,
+    },
+    /// Take a granted `role` away from `address`.
+    RevokeRole {
+        address: Address,
+        role: Role,
+    },
+    /// Let the admin `signer` spend up to `limit` CCD per `window` without a
+    /// proposal, a zero `limit` removes the allowance.
+    SetAllowance {
+        signer: Address,
+        limit: Amount,
+        window: Duration,
+    },
+    /// Lift a pause, the only proposal that can pass while paused.
+    Unpause,
+}
+
+// proposal <kind, address>
 // approve 
 #[derive(Serialize, SchemaType, Debug, PartialEq, Eq, Clone)]
 pub struct Proposal {
     pub index: u32,
-    pub amount: Amount,
-    pub receiptient: AccountAddress,
+    pub kind: ProposalKind,
     pub voted: Vec<Address>,
+    /// Combined weight of the approvals.
     pub approvals: u8,
-    pub fufilled: bool,
-    pub owner: Address
+    /// Admins that voted against the proposal.
+    pub rejections: Vec<Address>,
+    pub status: ProposalStatus,
+    pub owner: Address,
+    /// The proposal expires once the block time passes this point.
+    pub expiry: Timestamp,
+    /// Earliest execution time, set when the proposal first reaches the threshold.
+    pub executable_at: Option<Timestamp>,
+    /// What the called contract answered, set once a `ContractCall` is executed.
+    pub call_outcome: Option<CallOutcome>,
+    /// Execute the proposal as soon as its final approval lands.
+    pub auto_execute: bool,
+    /// CCD reserved for the proposal while it is approved, see `State::committed`.
+    pub reserved: Amount,
 }
 
 impl IsOwner for Proposal{}
 
+impl ProposalKind {
+    /// Total CCD the proposal sends out of the contract, `None` on overflow.
+    pub fn outflow(&self) -> Option<Amount> {
+        match self {
+            ProposalKind::Transfer { amount, .. } | ProposalKind::ContractCall { amount, .. } => Some(*amount),
+            ProposalKind::BatchTransfer(legs) => legs
+                .iter()
+                .try_fold(Amount::zero(), |total, leg| total.checked_add(leg.amount)),
+            _ => Some(Amount::zero()),
+        }
+    }
+
+    /// Whether the proposal sends CCD or tokens to `account`.
+    pub fn pays(&self, account: &AccountAddress) -> bool {
+        match self {
+            ProposalKind::Transfer { receiver, .. } | ProposalKind::TokenTransfer { receiver, .. } => receiver == account,
+            ProposalKind::BatchTransfer(legs) => legs.iter().any(|leg| leg.receiver == *account),
+            _ => false,
+        }
+    }
+
+    /// The events describing what executing this proposal changed.
+    pub fn events(&self) -> Vec<Event> {
+        match self {
+            ProposalKind::Transfer { .. }
+            | ProposalKind::BatchTransfer(_)
+            | ProposalKind::ContractCall { .. }
+            | ProposalKind::TokenTransfer { .. } => Vec::new(),
+            ProposalKind::Upgrade { module, .. } => vec![Event::Upgraded { module: *module }],
+            ProposalKind::AddSigner(signer) => vec![Event::SignerAdded { signer: *signer }],
+            ProposalKind::RemoveSigner(signer) => vec![Event::SignerRemoved { signer: *signer }],
+            ProposalKind::ReplaceSigner { old, new } => vec![
+                Event::SignerRemoved { signer: *old },
+                Event::SignerAdded { signer: *new },
+            ],
+            ProposalKind::ChangeWeight { signer, weight } => vec![Event::WeightChanged { signer: *signer, weight: *weight }],
+            ProposalKind::ChangeThreshold(threshold) => vec![Event::ThresholdChanged { threshold: *threshold }],
+            ProposalKind::GrantRole { address, role } => vec![Event::RoleGranted { address: *address, role: *role }],
+            ProposalKind::RevokeRole { address, role } => vec![Event::RoleRevoked { address: *address, role: *role }],
+            ProposalKind::SetAllowance { signer, limit, window } => vec![Event::AllowanceChanged {
+                signer: *signer,
+                limit: *limit,
+                window: *window,
+            }],
+            ProposalKind::Unpause => vec![Event::Unpaused],
+        }
+    }
+}
+
 impl Proposal {
 
-    pub fn new(
-        index:u32, amount: Amount, receiptient: AccountAddress,
-        approvals:u8, owner:Address, 
-    ) -> Self {
+    pub fn new(index:u32, kind: ProposalKind, owner:Address, expiry: Timestamp, auto_execute: bool) -> Self {
         let voted = Vec::new();
-        Proposal{index,amount,receiptient,voted,approvals,fufilled:false,owner}
+        let rejections = Vec::new();
+        let status = ProposalStatus::Pending;
+        let executable_at = None;
+        let call_outcome = None;
+        let reserved = Amount::zero();
+        Proposal{index,kind,voted,approvals:0,rejections,status,owner,expiry,executable_at,call_outcome,auto_execute,reserved}
     }
 
-    pub fn approve(&mut self,ctx: &ReceiveContext, votes_needed: usize )->Result<bool,Error> {
-       if self.voted.contains(&ctx.sender()) {
-            return Err(Error::AlreadyVoted)
+    /// Combined weight of the approvals cast by addresses that are still
+    /// among the weighted `signers`.
+    pub fn tally(&self, signers: &[(Address, u8)]) -> u8 {
+        weight_of(signers, &self.voted)
+    }
+
+    /// The status at block time `now`, an open proposal past its expiry is `Expired`.
+    pub fn status_at(&self, now: Timestamp) -> ProposalStatus {
+        match self.status {
+            ProposalStatus::Pending | ProposalStatus::Approved if now > self.expiry => ProposalStatus::Expired,
+            status => status,
+        }
+    }
+
+    /// Fails unless the proposal is still open for votes at block time `now`,
+    /// i.e. `Pending` or `Approved`.
+    pub fn ensure_open(&self, now: Timestamp) -> Result<(), Error> {
+        match self.status_at(now) {
+            ProposalStatus::Pending | ProposalStatus::Approved => Ok(()),
+            ProposalStatus::Executed => Err(Error::AlreadyExecuted),
+            ProposalStatus::Cancelled => Err(Error::ProposalCancelled),
+            ProposalStatus::Expired => Err(Error::ProposalExpired),
+            ProposalStatus::Failed => Err(Error::ProposalFailed),
+        }
+    }
+
+    /// Fails unless the proposal is `Approved`, still backed by `threshold`
+    /// weight of the current `signers`, and its timelock has elapsed.
+    pub fn ensure_executable(&self, now: Timestamp, signers: &[(Address, u8)], threshold: u8) -> Result<(), Error> {
+        self.ensure_open(now)?;
+        if self.status == ProposalStatus::Pending || self.tally(signers) < threshold {
+            return Err(Error::NotApproved)
+        }
+        if let Some(executable_at) = self.executable_at {
+            ensure!(now >= executable_at, Error::TimelockActive);
+        }
+        Ok(())
+    }
+
+    /// Records the approval of `signer` at block time `now`. The first time
+    /// the approving weight reaches `threshold` its timelock of `delay` starts.
+    pub fn approve(
+        &mut self, signer: Address, now: Timestamp, signers: &[(Address, u8)], threshold: u8, delay: Duration,
+    )->Result<bool,Error> {
+       self.ensure_open(now)?;
+       if self.voted.contains(&signer) || self.rejections.contains(&signer) {
+            Err(Error::AlreadyVoted)
        }else {
-            self.voted.push(ctx.sender());
-            self.approvals += 1;
-            Ok(self.approvals == votes_needed as u8)
+            self.voted.push(signer);
+            self.approvals = self.tally(signers);
+            if self.approvals >= threshold {
+                self.status = ProposalStatus::Approved;
+                if self.executable_at.is_none() {
+                    let executable_at = now.checked_add(delay)
+                        .unwrap_or(Timestamp::from_timestamp_millis(u64::MAX));
+                    self.executable_at = Some(executable_at);
+                }
+            }
+            Ok(self.status == ProposalStatus::Approved)
        }
     }
-    pub fn approved(&self, votes_needed: usize ) -> Result<bool,Error>{
-        let num_of_voted = votes_needed as u8;
-        Ok(self.approvals == num_of_voted)
+    /// Withdraws the sender's approval, moving an `Approved` proposal back to
+    /// `Pending` when it drops below `threshold`. Returns whether it is still approved.
+    pub fn revoke(&mut self, ctx: &ReceiveContext, signers: &[(Address, u8)], threshold: u8) -> Result<bool, Error> {
+        self.ensure_open(ctx.metadata().slot_time())?;
+        let sender = ctx.sender();
+        let position = self.voted.iter().position(|voter| *voter == sender)
+            .ok_or(Error::NotVoted)?;
+        self.voted.remove(position);
+        self.approvals = self.tally(signers);
+        if self.approvals < threshold {
+            self.status = ProposalStatus::Pending;
+        }
+        Ok(self.status == ProposalStatus::Approved)
+    }
+    /// Records the sender's vote against the proposal and cancels it once
+    /// the remaining weight of the `signers` can no longer reach `threshold`.
+    pub fn reject(&mut self, ctx: &ReceiveContext, signers: &[(Address, u8)], threshold: u8) -> Result<ProposalStatus, Error> {
+        self.ensure_open(ctx.metadata().slot_time())?;
+        let sender = ctx.sender();
+        if self.voted.contains(&sender) || self.rejections.contains(&sender) {
+            return Err(Error::AlreadyVoted)
+        }
+        self.rejections.push(sender);
+        let total: u32 = signers.iter().map(|(_, weight)| *weight as u32).sum();
+        let rejected = weight_of(signers, &self.rejections) as u32;
+        if total.saturating_sub(rejected) < threshold as u32 {
+            self.status = ProposalStatus::Cancelled;
+        }
+        Ok(self.status)
+    }
+
+    /// Cancels the proposal, only its owner or a `guardian` can do this. A
+    /// guardian cannot cancel an `Unpause`, or it could keep the contract paused.
+    pub fn cancel(&mut self, ctx: &ReceiveContext, guardian: bool) -> Result<(), Error> {
+        let guardian = guardian && self.kind != ProposalKind::Unpause;
+        ensure!(guardian || self.owner == ctx.sender(), Error::Unauthorized);
+        self.ensure_open(ctx.metadata().slot_time())?;
+        self.status = ProposalStatus::Cancelled;
+        Ok(())
+    }
+
+    /// Approval weight still missing before the proposal reaches `threshold`.
+    pub fn votes_remaining(&self, signers: &[(Address, u8)], threshold: u8) -> u8 {
+        threshold.saturating_sub(self.tally(signers))
     }
     
 }
 
+/// Combined weight of the `signers` that appear in `voters`.
+fn weight_of(signers: &[(Address, u8)], voters: &[Address]) -> u8 {
+    signers
+        .iter()
+        .filter(|(signer, _)| voters.contains(signer))
+        .fold(0u8, |total, (_, weight)| total.saturating_add(*weight))
+}
+
 impl State {    
-    pub fn new(state_builder: &mut StateBuilder, admins: Vec<Address> )-> Self {
+    pub fn new(state_builder: &mut StateBuilder, param: InitParameter)-> Self {
             State { 
+                version: STATE_VERSION,
                 transactions: state_builder.new_map(), 
-                admins: state_builder.new_box(admins) 
+                weights: {
+                    let mut weights = state_builder.new_map();
+                    for admin in param.admins.iter() {
+                        weights.insert(*admin, 1);
+                    }
+                    for (admin, weight) in param.weights {
+                        weights.insert(admin, weight);
+                    }
+                    weights
+                },
+                admins: state_builder.new_box(param.admins),
+                roles: {
+                    let mut roles = state_builder.new_map();
+                    for (address, granted) in param.roles {
+                        roles.insert(address, granted);
+                    }
+                    roles
+                },
+                threshold: param.threshold,
+                max_lifetime: param.max_lifetime,
+                execution_delay: param.execution_delay,
+                auto_execute: param.auto_execute,
+                paused: false,
+                next_proposal_id: 0,
+                token_balances: state_builder.new_map(),
+                nonces: state_builder.new_map(),
+                allowances: state_builder.new_map(),
+                deposits: state_builder.new_map(),
+                deposit_count: 0,
+                deposit_totals: state_builder.new_map(),
+                total_deposits: Amount::zero(),
+                committed: Amount::zero(),
             }
     }
 }
 
-/// Your smart contract errors.
+/// Errors returned by the contract entrypoints.
 #[derive(Debug, PartialEq, Eq, Reject, Serialize, SchemaType)]
 pub enum Error {
     /// Failed parsing the parameter.
     #[from(ParseError)]
     ParseParams,
-    /// Your error
-    YourError,
+    /// No proposal exists with the given index.
+    ProposalNotFound,
+    /// The sender already voted on the proposal.
     AlreadyVoted,
-    TransactionHasNotBeenApprovedOrAlreadyFufilled,
-    TransactionKeyAlreadyExists
+    /// The sender has not approved the proposal.
+    NotVoted,
+    /// The proposal has not reached the threshold yet.
+    NotApproved,
+    /// The proposal has already been executed.
+    AlreadyExecuted,
+    /// The proposal has been cancelled.
+    ProposalCancelled,
+    /// The proposal passed its deadline.
+    ProposalExpired,
+    /// A previous execution of the proposal failed.
+    ProposalFailed,
+    /// The threshold is zero or larger than the total weight of the admins.
+    InvalidThreshold,
+    /// A weight is zero or the total weight of the admins exceeds 255.
+    InvalidWeight,
+    /// The role is `Approver`, already granted, or not granted when revoking.
+    InvalidRole,
+    /// A permit or signed proposal was signed for another contract.
+    WrongContract,
+    /// A permit is past its expiry.
+    PermitExpired,
+    /// A permit does not carry the signer's current nonce.
+    NonceMismatch,
+    /// A signature of a permit or signed proposal does not match the
+    /// signer's account keys.
+    InvalidSignature,
+    /// The allowance has a limit but an empty window.
+    InvalidAllowance,
+    /// The spend exceeds what is left of the sender's allowance.
+    AllowanceExceeded,
+    /// The chain refused the upgrade, e.g. the module does not exist.
+    UpgradeFailed,
+    /// The migration after an upgrade was rejected.
+    MigrationFailed,
+    /// The state has a newer version than this module understands.
+    InvalidVersion,
+    /// The contract is paused.
+    Paused,
+    /// The contract is not paused.
+    NotPaused,
+    /// A deposit memo is longer than `MAX_MEMO_LENGTH` bytes.
+    MemoTooLong,
+    /// No deposit exists with the given sequence number.
+    DepositNotFound,
+    /// A deposit must send some CCD.
+    EmptyDeposit,
+    /// Approving the proposal would commit more CCD than the contract holds.
+    Overcommitted,
+    /// The proposal is not expired, so its reservation cannot be released.
+    NotExpired,
+    /// `Upgrade` proposals are executed by `upgrade`, every other proposal by `transfer`.
+    WrongEntrypoint,
+    /// A signed proposal does not carry the id the next proposal will get.
+    WrongProposalIndex,
+    /// A signed proposal is past its expiry.
+    SignaturesExpired,
+    /// A signed proposal comes without any signatures.
+    NoSignatures,
+    /// The sender is not allowed to perform this action.
+    Unauthorized,
+    /// The deadline is not in the future or exceeds the maximum lifetime.
+    InvalidDeadline,
+    /// The proposal is approved but its execution delay has not elapsed.
+    TimelockActive,
+    /// The address is already an admin.
+    SignerAlreadyExists,
+    /// The address is not an admin.
+    SignerNotFound,
+    /// The contract balance does not cover the transfer.
+    InsufficientFunds,
+    /// The CCD transfer was refused by the chain.
+    TransferFailed,
+    /// A batch transfer has no legs or its total overflows.
+    InvalidBatch,
+    /// The token contract refused the CIS-2 transfer.
+    TokenTransferFailed,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+}
+
+impl From<TransferError> for Error {
+    fn from(te: TransferError) -> Self {
+        match te {
+            TransferError::AmountTooLarge => Self::InsufficientFunds,
+            TransferError::MissingAccount => Self::TransferFailed,
+        }
+    }
+}
+
+impl From<LogError> for Error {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
 }
 #[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
 pub struct InitParameter {
-    pub admins : Vec<Address>
+    pub admins : Vec<Address>,
+    /// Voting weight of admins that count for more than one vote, the others weigh 1.
+    pub weights: Vec<(Address, u8)>,
+    /// Approval weight required to execute a proposal, between 1 and the
+    /// total weight of the admins.
+    pub threshold: u8,
+    /// Roles granted to addresses besides the admins, e.g. proposers or
+    /// executors, usually empty. `Approver` cannot be granted.
+    pub roles: Vec<(Address, Vec<Role>)>,
+    /// Longest time a proposal stays open, also its default lifetime.
+    pub max_lifetime: Duration,
+    /// Delay between a proposal reaching the threshold and its execution.
+    pub execution_delay: Duration,
+    /// Execute every proposal as soon as its final approval lands.
+    pub auto_execute: bool,
 }
 #[derive(Serialize, SchemaType)]
 pub struct TxParameter {
-    pub index: u32,
     pub receiver : AccountAddress,
     pub amount: Amount,
+    /// When the proposal expires, defaults to the contract's maximum lifetime.
+    pub deadline: Option<Timestamp>,
+    /// Execute the proposal as soon as its final approval lands.
+    pub auto_execute: bool,
 }
 
-impl TxParameter {
-    pub fn default() -> Self {
-        TxParameter { index: 0, receiver: AccountAddress([0u8; 32]), amount: (Amount { micro_ccd: 0 }) }
+impl Default for TxParameter {
+    fn default() -> Self {
+        TxParameter { receiver: AccountAddress([0u8; 32]), amount: (Amount { micro_ccd: 0 }), deadline: None, auto_execute: false }
     }
-    pub fn new(index:u32, receiver:AccountAddress,amount:u64) -> Self {
-        TxParameter { index, receiver, amount: Amount { micro_ccd: amount } }
+}
+
+impl TxParameter {
+    pub fn new(receiver:AccountAddress,amount:u64) -> Self {
+        TxParameter { receiver, amount: Amount { micro_ccd: amount }, deadline: None, auto_execute: false }
     }
 }
 
@@ -119,6 +944,119 @@ pub struct ApproveParameter {
     pub index: u32,
 }
 
+/// Most proposals `list_proposals` returns in one page, and most proposal
+/// ids it visits in one call.
+pub const MAX_PAGE_SIZE: u32 = 50;
+
+/// Selects a page of proposals, see `list_proposals`. Every filter that is
+/// set must match.
+#[derive(Serialize, SchemaType)]
+pub struct ListParameter {
+    /// Index to start from, `0` for the first page.
+    pub cursor: u32,
+    /// Most proposals to return, capped at `MAX_PAGE_SIZE`, `0` for the cap.
+    pub page_size: u32,
+    pub status: Option<ProposalStatus>,
+    pub owner: Option<Address>,
+    /// Only proposals that send CCD or tokens to this account.
+    pub receiver: Option<AccountAddress>,
+}
+
+/// A compact view of a proposal returned by `list_proposals`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub struct ProposalSummary {
+    pub index: u32,
+    pub status: ProposalStatus,
+    pub owner: Address,
+    /// CCD the proposal sends out of the contract.
+    pub amount: Amount,
+    pub approvals: u8,
+    pub expiry: Timestamp,
+}
+
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub struct ProposalPage {
+    pub proposals: Vec<ProposalSummary>,
+    /// Cursor of the next page, `None` once every proposal was visited.
+    pub next_cursor: Option<u32>,
+}
+
+/// The CCD balance of the contract split into what is committed to approved
+/// proposals and what is still available, returned by `get_balance`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub struct BalanceSummary {
+    pub balance: Amount,
+    pub committed: Amount,
+    pub available: Amount,
+}
+
+#[derive(Serialize, SchemaType)]
+pub struct SpendParameter {
+    pub receiver: AccountAddress,
+    pub amount: Amount,
+}
+
+#[derive(Serialize, SchemaType)]
+pub struct TokenBalanceParameter {
+    pub token: ContractAddress,
+    pub token_id: TokenIdVec,
+}
+
+#[derive(Serialize, SchemaType)]
+pub struct ProposeParameter {
+    pub kind: ProposalKind,
+    /// When the proposal expires, defaults to the contract's maximum lifetime.
+    pub deadline: Option<Timestamp>,
+    /// Execute the proposal as soon as its final approval lands.
+    pub auto_execute: bool,
+}
+
+/// The approval an admin signs off-chain, see `permit`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq, Clone)]
+pub struct PermitMessage {
+    /// The contract the approval is meant for.
+    pub contract_address: ContractAddress,
+    /// Must match the signer's current nonce, see `get_nonce`.
+    pub nonce: u64,
+    /// The permit is rejected after this time.
+    pub timestamp: Timestamp,
+    /// The proposal to approve.
+    pub index: u32,
+}
+
+/// A `PermitMessage` together with its signer and their signature.
+#[derive(Serialize, SchemaType)]
+pub struct Permit {
+    pub signer: AccountAddress,
+    pub signature: AccountSignatures,
+    pub message: PermitMessage,
+}
+
+#[derive(Serialize, SchemaType)]
+pub struct PermitParameter {
+    pub permits: Vec<Permit>,
+}
+
+/// A proposal admins sign off-chain, see `execute_signed`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq, Clone)]
+pub struct SignedProposal {
+    /// The contract the proposal is meant for.
+    pub contract_address: ContractAddress,
+    /// Must be the index the proposal gets, see `get_next_proposal_id`,
+    /// so the signatures cannot be replayed.
+    pub index: u32,
+    /// The signatures are rejected after this time.
+    pub timestamp: Timestamp,
+    pub kind: ProposalKind,
+}
+
+#[derive(Serialize, SchemaType)]
+pub struct ExecuteSignedParameter {
+    pub message: SignedProposal,
+    /// Admins and their signatures of `message`.
+    pub signatures: Vec<(AccountAddress, AccountSignatures)>,
+}
+
 impl ApproveParameter {
     pub fn new(index: u32) -> Self {
         Self {index}
@@ -129,117 +1067,699 @@ impl ApproveParameter {
 /// Init function that creates a new smart contract.
 #[init(
     contract = "ccd_multisig",
-    parameter="InitParameter"
+    parameter="InitParameter",
+    event = "Event",
+    error = "Error"
 )]
-fn init(ctx: &InitContext, state_builder: &mut StateBuilder) -> InitResult<State> {
+fn init(ctx: &InitContext, state_builder: &mut StateBuilder) -> Result<State, Error> {
     // Your code
     let param: InitParameter = ctx.parameter_cursor().get()?;
-    let admins = param.admins;
-    let state = State::new(state_builder,admins);
+    for (position, admin) in param.admins.iter().enumerate() {
+        ensure!(!param.admins[..position].contains(admin), Error::SignerAlreadyExists);
+    }
+    for (admin, weight) in param.weights.iter() {
+        ensure!(param.admins.contains(admin), Error::SignerNotFound);
+        ensure!(*weight > 0, Error::InvalidWeight);
+    }
+    for (_, roles) in param.roles.iter() {
+        ensure!(!roles.contains(&Role::Approver), Error::InvalidRole);
+    }
+    let state = State::new(state_builder,param);
+    let total = state.total_weight();
+    ensure!(total <= u8::MAX as u32, Error::InvalidWeight);
+    let threshold = state.threshold;
+    if threshold == 0 || threshold as u32 > total {
+        return Err(Error::InvalidThreshold)
+    }
     Ok(state)   
 }
 
 
-#[receive( contract = "ccd_multisig", name = "transfer", parameter="ApproveParameter", mutable)]
-fn transfer(ctx: &ReceiveContext, host: &mut Host<State>)-> ReceiveResult<()> {
+/// Executes an approved proposal and returns its resulting status, only
+/// admins and executors can call it. Governance
+/// proposals update the admins or threshold, a transfer the chain refuses
+/// (e.g. to a missing account) marks the proposal `Failed`. Batch transfers
+/// are atomic: if any leg fails the whole call is rejected. A contract call
+/// records the callee's answer on the proposal and is `Failed` if it rejects.
+/// `Upgrade` proposals are executed by `upgrade` instead.
+#[receive( contract = "ccd_multisig", name = "transfer", parameter="ApproveParameter", return_value = "ProposalStatus", mutable, enable_logger, error = "Error")]
+fn transfer(ctx: &ReceiveContext, host: &mut Host<State>, logger: &mut Logger)-> Result<ProposalStatus, Error> {
     let param:ApproveParameter = ctx.parameter_cursor().get()?;
-    let votes_needed = host.state().admins.len();
-    let index = param.index;
-    let approved = host.state_mut()
-        .transactions
-        .get(&index).unwrap().approved(votes_needed).unwrap();
-    let not_fufilled = host.state_mut()
+    ensure!(host.state().has_role(&ctx.sender(), Role::Executor), Error::Unauthorized);
+    execute(ctx, host, logger, param.index)
+}
+
+/// Executes the approved proposal `index`, shared by `transfer` and `execute_signed`.
+fn execute(ctx: &ReceiveContext, host: &mut Host<State>, logger: &mut Logger, index: u32) -> Result<ProposalStatus, Error> {
+    let proposal = host.state()
         .transactions
-        .get_mut(&index).unwrap().fufilled == false;
-    let amount = host.state_mut()
+        .get(&index).ok_or(Error::ProposalNotFound)?.clone();
+    ensure!(!matches!(proposal.kind, ProposalKind::Upgrade { .. }), Error::WrongEntrypoint);
+    host.state().ensure_active(&proposal.kind)?;
+    let signers = host.state().signers();
+    let threshold = host.state().threshold;
+    proposal.ensure_executable(ctx.metadata().slot_time(), &signers, threshold)?;
+    // Mark the proposal executed before running it, so a contract it calls
+    // cannot execute it a second time.
+    host.state_mut()
         .transactions
-        .get(&index).unwrap().amount;
-    let receiptient = host.state_mut()
+        .get_mut(&index).ok_or(Error::ProposalNotFound)?.status = ProposalStatus::Executed;
+    host.state_mut().release(index)?;
+    let mut call_outcome = None;
+    let status = match proposal.kind {
+        ProposalKind::Transfer { receiver, amount } => {
+            ensure!(host.self_balance() >= amount, Error::InsufficientFunds);
+            match host.invoke_transfer(&receiver, amount) {
+                Ok(()) => ProposalStatus::Executed,
+                Err(TransferError::MissingAccount) => ProposalStatus::Failed,
+                Err(error) => return Err(error.into()),
+            }
+        }
+        ProposalKind::BatchTransfer(ref legs) => {
+            let total = proposal.kind.outflow().ok_or(Error::InvalidBatch)?;
+            ensure!(host.self_balance() >= total, Error::InsufficientFunds);
+            // Every receiver must exist before any leg is paid, so a batch
+            // fails as a whole even when the error is not passed on.
+            for leg in legs {
+                ensure!(host.account_balance(leg.receiver).is_ok(), Error::TransferFailed);
+            }
+            for leg in legs {
+                host.invoke_transfer(&leg.receiver, leg.amount)?;
+            }
+            ProposalStatus::Executed
+        }
+        ProposalKind::TokenTransfer { token, ref token_id, amount, receiver } => {
+            let key = (token, token_id.clone());
+            let balance = host.state().token_balances.get(&key).map(|balance| *balance).unwrap_or_default();
+            ensure!(balance >= amount, Error::InsufficientFunds);
+            let transfer = Transfer {
+                token_id: token_id.clone(),
+                amount,
+                from: Address::Contract(ctx.self_address()),
+                to: Receiver::Account(receiver),
+                data: AdditionalData::empty(),
+            };
+            host.invoke_contract(
+                &token, &TransferParams(vec![transfer]), EntrypointName::new_unchecked("transfer"), Amount::zero(),
+            ).map_err(|_| Error::TokenTransferFailed)?;
+            host.state_mut().token_balances.insert(key, balance - amount);
+            ProposalStatus::Executed
+        }
+        ProposalKind::ContractCall { ref address, ref entrypoint, ref parameter, amount } => {
+            ensure!(host.self_balance() >= amount, Error::InsufficientFunds);
+            let result = host.invoke_contract_raw(
+                address, parameter.as_parameter(), entrypoint.as_entrypoint_name(), amount,
+            );
+            let (success, return_value) = match result {
+                Ok((_, return_value)) => (true, return_value),
+                Err(CallContractError::AmountTooLarge) => return Err(Error::InsufficientFunds),
+                Err(CallContractError::LogicReject { return_value, .. }) => (false, Some(return_value)),
+                Err(_) => (false, None),
+            };
+            call_outcome = Some(CallOutcome {
+                success,
+                return_value: return_value.map(read_return_value).unwrap_or_default(),
+            });
+            if success { ProposalStatus::Executed } else { ProposalStatus::Failed }
+        }
+        ref kind => {
+            host.state_mut().apply_governance(kind)?;
+            for event in kind.events() {
+                logger.log(&event)?;
+            }
+            ProposalStatus::Executed
+        }
+    };
+    let mut executed = host.state_mut()
         .transactions
-        .get(&index).unwrap().receiptient;
-    if host.self_balance() < amount {
-        bail!()
-    }
-    match (approved,not_fufilled) {
-        (true,true) => {
-            host.state_mut()
-            .transactions
-            .get_mut(&index).unwrap().fufilled = true;
-            let response = host.invoke_transfer(&receiptient, amount);
-            match response{
-                Ok(()) => Ok(()),
-                Err(_) => bail!()
-            }
-        },
-        _ => bail!()
+        .get_mut(&index).ok_or(Error::ProposalNotFound)?;
+    executed.status = status;
+    executed.call_outcome = call_outcome;
+    drop(executed);
+    if status == ProposalStatus::Executed {
+        logger.log(&Event::Executed { index })?;
+    } else {
+        logger.log(&Event::ExecutionFailed { index })?;
+    }
+    Ok(status)
+}
+
+/// Executes the approved `Upgrade` proposal `index`: switches the contract to
+/// the new module and calls the migration, if any. It is `low_level` because
+/// the migration may change the state layout, so the proposal is marked
+/// executed and the events are logged before upgrading, and no state is
+/// touched afterwards. A refused module or migration rejects the whole call.
+/// Only admins and executors can call it.
+#[receive(contract = "ccd_multisig", name = "upgrade", parameter="ApproveParameter", low_level, enable_logger, error = "Error")]
+fn upgrade(ctx: &ReceiveContext, host: &mut LowLevelHost, logger: &mut Logger) -> Result<(), Error> {
+    let param:ApproveParameter = ctx.parameter_cursor().get()?;
+    let index = param.index;
+    let mut state: State = host.state().read_root()?;
+    ensure!(state.has_role(&ctx.sender(), Role::Executor), Error::Unauthorized);
+    let proposal = state.transactions.get(&index).ok_or(Error::ProposalNotFound)?.clone();
+    let ProposalKind::Upgrade { module, ref migration } = proposal.kind else {
+        return Err(Error::WrongEntrypoint)
+    };
+    state.ensure_active(&proposal.kind)?;
+    proposal.ensure_executable(ctx.metadata().slot_time(), &state.signers(), state.threshold)?;
+    state.transactions.get_mut(&index).ok_or(Error::ProposalNotFound)?.status = ProposalStatus::Executed;
+    state.release(index)?;
+    host.state_mut().write_root(&state);
+    drop(state);
+    for event in proposal.kind.events() {
+        logger.log(&event)?;
+    }
+    logger.log(&Event::Executed { index })?;
+    host.upgrade(module).map_err(|_| Error::UpgradeFailed)?;
+    if let Some((entrypoint, parameter)) = migration {
+        host.invoke_contract_raw(
+            &ctx.self_address(), parameter.as_parameter(), entrypoint.as_entrypoint_name(), Amount::zero(),
+        ).map_err(|_| Error::MigrationFailed)?;
+    }
+    Ok(())
+}
+
+/// Reads the whole return value of a contract call.
+fn read_return_value(mut return_value: ExternCallResponse) -> Vec<u8> {
+    let mut bytes = vec![0u8; return_value.size() as usize];
+    match return_value.read_exact(&mut bytes) {
+        Ok(()) => bytes,
+        Err(_) => Vec::new(),
     }
-    
 }
 
-/// This function recieves CCD from anybody
-#[receive(contract = "ccd_multisig", name = "insert", payable)]
-#[allow(unused_variables)]
+/// Migrates the state to `STATE_VERSION` after an upgrade. Only the contract
+/// itself can call it, as the migration of an `Upgrade` proposal, and it
+/// refuses state from a newer version. State of an older layout cannot be
+/// read by this module, the module it is upgraded from must convert it.
+#[receive(contract = "ccd_multisig", name = "migrate", mutable, error = "Error")]
+fn migrate(ctx: &ReceiveContext, host: &mut Host<State>) -> Result<(), Error> {
+    ensure!(ctx.sender() == Address::Contract(ctx.self_address()), Error::Unauthorized);
+    ensure!(host.state().version <= STATE_VERSION, Error::InvalidVersion);
+    host.state_mut().version = STATE_VERSION;
+    Ok(())
+}
+
+/// This function recieves CCD from anybody and records the deposit in the
+/// ledger, with an optional memo. An empty parameter means no memo. Deposits
+/// of zero CCD are refused so the ledger cannot be filled for free.
+#[receive(contract = "ccd_multisig", name = "insert", parameter = "Option<String>", mutable, payable, enable_logger, error = "Error")]
 fn insert(
-    ctx: &ReceiveContext,host: &Host<State>,amount: Amount
-) -> ReceiveResult<()> {
+    ctx: &ReceiveContext,host: &mut Host<State>,amount: Amount, logger: &mut Logger
+) -> Result<(), Error> {
+    let memo: Option<String> = if ctx.parameter_cursor().size() == 0 {
+        None
+    } else {
+        ctx.parameter_cursor().get()?
+    };
+    ensure!(amount > Amount::zero(), Error::EmptyDeposit);
+    ensure!(memo.as_ref().map(|memo| memo.len() <= MAX_MEMO_LENGTH).unwrap_or(true), Error::MemoTooLong);
+    let sender = ctx.sender();
+    let state = host.state_mut();
+    let deposit = Deposit { sender, amount, time: ctx.metadata().slot_time(), memo };
+    state.deposits.insert(state.deposit_count, deposit);
+    state.deposit_count += 1;
+    let total = state.deposit_totals.get(&sender).map(|total| *total).unwrap_or(Amount::zero());
+    state.deposit_totals.insert(sender, total + amount);
+    state.total_deposits += amount;
+    logger.log(&Event::Deposit { sender, amount })?;
     Ok(())
 }
 
-/// initialises a new transaction pending approval
-#[receive(contract = "ccd_multisig", name = "create_tx", parameter="TxParameter", mutable)]
-pub fn create_tx(ctx: &ReceiveContext,host: &mut Host<State>)-> Result<u32, Error>{
+/// CIS-2 receive hook: accepts tokens sent to the contract and tracks the
+/// balance of each token. Only contracts can call it.
+#[receive(
+    contract = "ccd_multisig", name = "onReceivingCIS2",
+    parameter = "OnReceivingCis2Params<TokenIdVec, TokenAmountU256>",
+    mutable, enable_logger, error = "Error"
+)]
+fn on_receiving_cis2(ctx: &ReceiveContext, host: &mut Host<State>, logger: &mut Logger) -> Result<(), Error> {
+    let token = match ctx.sender() {
+        Address::Contract(token) => token,
+        Address::Account(_) => return Err(Error::Unauthorized),
+    };
+    let param: OnReceivingCis2Params<TokenIdVec, TokenAmountU256> = ctx.parameter_cursor().get()?;
+    let key = (token, param.token_id.clone());
+    let balance = host.state().token_balances.get(&key).map(|balance| *balance).unwrap_or_default();
+    host.state_mut().token_balances.insert(key, balance + param.amount);
+    logger.log(&Event::TokenDeposit {
+        token,
+        token_id: param.token_id,
+        amount: param.amount,
+        from: param.from,
+    })?;
+    Ok(())
+}
+
+/// Freezes the contract at once: creating, approving and executing
+/// proposals and spending allowances are blocked until an `Unpause`
+/// proposal is executed. Guardians and admins can call it.
+#[receive(contract = "ccd_multisig", name = "pause", mutable, enable_logger, error = "Error")]
+pub fn pause(ctx: &ReceiveContext,host: &mut Host<State>, logger: &mut Logger)-> Result<(), Error>{
+    let sender = ctx.sender();
+    let state = host.state();
+    ensure!(state.is_owner(&sender) || state.has_role(&sender, Role::Guardian), Error::Unauthorized);
+    ensure!(!state.paused, Error::Paused);
+    host.state_mut().paused = true;
+    logger.log(&Event::Paused { by: sender })?;
+    Ok(())
+}
+
+/// Lets an admin send CCD within their allowance without a proposal. The
+/// allowance resets once its window has passed since the window's first spend.
+#[receive(contract = "ccd_multisig", name = "spend", parameter="SpendParameter", mutable, enable_logger, error = "Error")]
+pub fn spend(ctx: &ReceiveContext,host: &mut Host<State>, logger: &mut Logger)-> Result<(), Error>{
+    let param:SpendParameter = ctx.parameter_cursor().get()?;
+    let signer = ctx.sender();
+    ensure!(host.state().is_owner(&signer), Error::Unauthorized);
+    ensure!(!host.state().paused, Error::Paused);
+    let allowance = host.state().allowances.get(&signer).map(|allowance| *allowance)
+        .ok_or(Error::Unauthorized)?
+        .at(ctx.metadata().slot_time());
+    ensure!(param.amount <= allowance.remaining(), Error::AllowanceExceeded);
+    ensure!(host.state().available(host.self_balance()) >= param.amount, Error::InsufficientFunds);
+    host.state_mut().allowances.insert(signer, Allowance { spent: allowance.spent + param.amount, ..allowance });
+    host.invoke_transfer(&param.receiver, param.amount)?;
+    logger.log(&Event::Spent { signer, receiver: param.receiver, amount: param.amount })?;
+    Ok(())
+}
+
+/// Stores a new pending proposal on behalf of `owner` and returns its index.
+/// It is flagged to execute on its final approval if `auto_execute` or the
+/// contract-wide flag is set.
+fn add_proposal(
+    ctx: &ReceiveContext, host: &mut Host<State>, logger: &mut Logger,
+    owner: Address, kind: ProposalKind, deadline: Option<Timestamp>, auto_execute: bool,
+) -> Result<u32, Error> {
+    ensure!(host.state().can_propose(&owner), Error::Unauthorized);
+    host.state().ensure_active(&kind)?;
+    let now = ctx.metadata().slot_time();
+    let state = host.state_mut();
+    let latest = now.checked_add(state.max_lifetime).ok_or(Error::InvalidDeadline)?;
+    let expiry = deadline.unwrap_or(latest);
+    ensure!(now < expiry && expiry <= latest, Error::InvalidDeadline);
+    let index = state.next_proposal_id;
+    let auto_execute = auto_execute || state.auto_execute;
+    let proposal = Proposal::new(index,kind,owner,expiry,auto_execute);
+    state.transactions.insert(index, proposal);
+    state.next_proposal_id += 1;
+    logger.log(&Event::ProposalCreated { index, owner })?;
+    Ok(index)
+}
+
+/// initialises a new transaction pending approval and returns the index the
+/// contract assigned to it, only admins and proposers can call it
+#[receive(contract = "ccd_multisig", name = "create_tx", parameter="TxParameter", return_value = "u32", mutable, enable_logger, error = "Error")]
+pub fn create_tx(ctx: &ReceiveContext,host: &mut Host<State>, logger: &mut Logger)-> Result<u32, Error>{
     let param:TxParameter = ctx.parameter_cursor().get()?;
-    if let None = host.state().transactions.get(&param.index){
-        let proposal = Proposal::new(param.index,param.amount,param.receiver,0,ctx.sender());
-            host.state_mut().transactions.insert(param.index, proposal);
-            Ok(param.index)
-    }else{
-        Err(Error::TransactionKeyAlreadyExists)        
+    let kind = ProposalKind::Transfer { receiver: param.receiver, amount: param.amount };
+    add_proposal(ctx, host, logger, ctx.sender(), kind, param.deadline, param.auto_execute)
+}
+
+/// Creates a proposal of any kind, e.g. to add or remove admins or change the
+/// threshold. It goes through the same approve and `transfer` flow as CCD transfers.
+#[receive(contract = "ccd_multisig", name = "propose", parameter="ProposeParameter", return_value = "u32", mutable, enable_logger, error = "Error")]
+pub fn propose(ctx: &ReceiveContext,host: &mut Host<State>, logger: &mut Logger)-> Result<u32, Error>{
+    let param:ProposeParameter = ctx.parameter_cursor().get()?;
+    host.state().check_proposal(&param.kind)?;
+    add_proposal(ctx, host, logger, ctx.sender(), param.kind, param.deadline, param.auto_execute)
+}
+
+/// Approves a proposal and returns whether it reached the threshold. A
+/// proposal flagged `auto_execute` is executed right away, see `auto_execute`.
+#[receive(contract = "ccd_multisig", name = "approve", parameter="ApproveParameter", return_value = "bool", mutable, enable_logger, error = "Error")]
+pub fn approve(ctx: &ReceiveContext,host: &mut Host<State>, logger: &mut Logger)-> Result<bool, Error>{
+    let param:ApproveParameter = ctx.parameter_cursor().get()?;
+    let approved = approve_as(host, logger, ctx.sender(), param.index, ctx.metadata().slot_time())?;
+    if approved {
+        auto_execute(ctx, host, logger, param.index)?;
     }
-    
+    Ok(approved)
+}
+
+/// Executes proposal `index` if it is flagged `auto_execute` and nothing
+/// holds it back: it is approved, no timelock is running, a governance
+/// change still applies and the balance covers the CCD or tokens it sends.
+/// Otherwise, or if the execution fails, it is left `Approved` for
+/// `transfer` and the approval stands. Upgrades are never executed here,
+/// they wait for `upgrade`.
+fn auto_execute(ctx: &ReceiveContext, host: &mut Host<State>, logger: &mut Logger, index: u32) -> Result<(), Error> {
+    let proposal = host.state().transactions.get(&index).ok_or(Error::ProposalNotFound)?.clone();
+    if !proposal.auto_execute {
+        return Ok(())
+    }
+    let state = host.state();
+    let signers = state.signers();
+    let covered = match proposal.kind {
+        ProposalKind::TokenTransfer { token, ref token_id, amount, .. } => state
+            .token_balances
+            .get(&(token, token_id.clone()))
+            .map(|balance| *balance >= amount)
+            .unwrap_or(false),
+        ref kind => kind.outflow().map(|outflow| outflow <= host.self_balance()).unwrap_or(false),
+    };
+    let applies = !matches!(proposal.kind, ProposalKind::Upgrade { .. })
+        && state.check_proposal(&proposal.kind).is_ok();
+    let executable = proposal.ensure_executable(ctx.metadata().slot_time(), &signers, state.threshold).is_ok();
+    if covered && applies && executable {
+        let committed = host.state().committed;
+        if execute(ctx, host, logger, index).is_err() {
+            // Undo marking the proposal executed and releasing its reservation.
+            let state = host.state_mut();
+            state.transactions.insert(index, proposal);
+            state.committed = committed;
+        }
+    }
+    Ok(())
+}
+
+/// Records the approval of `signer`, shared by `approve` and `permit`. The
+/// approval that makes a proposal approved reserves the CCD it sends.
+fn approve_as(
+    host: &mut Host<State>, logger: &mut Logger, signer: Address, index: u32, now: Timestamp,
+) -> Result<bool, Error> {
+    ensure!(host.state().is_owner(&signer), Error::Unauthorized);
+    let state = host.state();
+    state.ensure_active(&state.transactions.get(&index).ok_or(Error::ProposalNotFound)?.kind)?;
+    let signers = host.state().signers();
+    let threshold = host.state().threshold;
+    let delay = host.state().execution_delay;
+    let mut proposal = host.state_mut().transactions.get_mut(&index)
+        .ok_or(Error::ProposalNotFound)?;
+    let approved = proposal.approve(signer,now,&signers,threshold,delay)?;
+    drop(proposal);
+    let balance = host.self_balance();
+    host.state_mut().reserve(index, balance)?;
+    logger.log(&Event::Approved { index, signer })?;
+    Ok(approved)
+}
+
+/// Applies approvals that admins signed off-chain, so a relayer can submit
+/// them and pay the fees. Each permit must be meant for this contract, not
+/// expired, carry the signer's current nonce and be signed with the signer's
+/// account keys. Returns for each permit whether its proposal is approved.
+#[receive(contract = "ccd_multisig", name = "permit", parameter="PermitParameter", return_value = "Vec<bool>", mutable, enable_logger, crypto_primitives, error = "Error")]
+pub fn permit(
+    ctx: &ReceiveContext, host: &mut Host<State>, logger: &mut Logger, crypto_primitives: &impl HasCryptoPrimitives,
+)-> Result<Vec<bool>, Error>{
+    let param:PermitParameter = ctx.parameter_cursor().get()?;
+    let now = ctx.metadata().slot_time();
+    let mut approved = Vec::with_capacity(param.permits.len());
+    for permit in param.permits {
+        let message = permit.message;
+        ensure!(message.contract_address == ctx.self_address(), Error::WrongContract);
+        ensure!(now <= message.timestamp, Error::PermitExpired);
+        let nonce = host.state().nonces.get(&permit.signer).map(|nonce| *nonce).unwrap_or(0);
+        ensure!(message.nonce == nonce, Error::NonceMismatch);
+        host.state_mut().nonces.insert(permit.signer, nonce + 1);
+        check_signature(host, crypto_primitives, permit.signer, &permit.signature, &message)?;
+        let approval = approve_as(host, logger, Address::Account(permit.signer), message.index, now)?;
+        if approval {
+            auto_execute(ctx, host, logger, message.index)?;
+        }
+        approved.push(approval);
+    }
+    Ok(approved)
 }
 
-#[receive(contract = "ccd_multisig", name = "approve", parameter="ApproveParameter", mutable)]
-pub fn approve(ctx: &ReceiveContext,host: &mut Host<State>)-> ReceiveResult<bool>{
+/// Fails unless `signature` is the signer's signature of `message`, hashed
+/// the way wallets do: the signer's address and eight zero bytes followed by
+/// the serialized message, hashed with SHA-256.
+fn check_signature(
+    host: &Host<State>, crypto_primitives: &impl HasCryptoPrimitives, signer: AccountAddress,
+    signature: &AccountSignatures, message: &impl Serial,
+) -> Result<(), Error> {
+    let mut bytes = signer.0.to_vec();
+    bytes.extend_from_slice(&[0u8; 8]);
+    message.serial(&mut bytes).map_err(|_| Error::InvalidSignature)?;
+    let hash = crypto_primitives.hash_sha2_256(&bytes).0;
+    let valid = host.check_account_signature(signer, signature, &hash).unwrap_or(false);
+    ensure!(valid, Error::InvalidSignature);
+    Ok(())
+}
+
+/// Creates, approves and executes a proposal in one call from a bundle of
+/// admin signatures over a `SignedProposal`. The signatures must reach the
+/// threshold, the proposal is owned by the first signer. If a timelock
+/// applies the proposal is left `Approved` for `transfer` to execute later,
+/// an `Upgrade` is always left for `upgrade`.
+/// Only admins and executors can call it.
+#[receive(contract = "ccd_multisig", name = "execute_signed", parameter="ExecuteSignedParameter", return_value = "ProposalStatus", mutable, enable_logger, crypto_primitives, error = "Error")]
+pub fn execute_signed(
+    ctx: &ReceiveContext, host: &mut Host<State>, logger: &mut Logger, crypto_primitives: &impl HasCryptoPrimitives,
+)-> Result<ProposalStatus, Error>{
+    let param:ExecuteSignedParameter = ctx.parameter_cursor().get()?;
+    ensure!(host.state().has_role(&ctx.sender(), Role::Executor), Error::Unauthorized);
+    let message = param.message;
+    let now = ctx.metadata().slot_time();
+    ensure!(message.contract_address == ctx.self_address(), Error::WrongContract);
+    ensure!(now <= message.timestamp, Error::SignaturesExpired);
+    ensure!(message.index == host.state().next_proposal_id, Error::WrongProposalIndex);
+    let (owner, _) = param.signatures.first().ok_or(Error::NoSignatures)?;
+    host.state().check_proposal(&message.kind)?;
+    let index = add_proposal(ctx, host, logger, Address::Account(*owner), message.kind.clone(), None, false)?;
+    let mut approved = false;
+    for (signer, signature) in param.signatures.iter() {
+        check_signature(host, crypto_primitives, *signer, signature, &message)?;
+        approved = approve_as(host, logger, Address::Account(*signer), index, now)?;
+    }
+    ensure!(approved, Error::NotApproved);
+    let signers = host.state().signers();
+    let threshold = host.state().threshold;
+    let proposal = host.state().transactions.get(&index).ok_or(Error::ProposalNotFound)?.clone();
+    match proposal.ensure_executable(now, &signers, threshold) {
+        Err(Error::TimelockActive) => Ok(ProposalStatus::Approved),
+        Ok(()) if matches!(proposal.kind, ProposalKind::Upgrade { .. }) => Ok(ProposalStatus::Approved),
+        Err(error) => Err(error),
+        Ok(()) => execute(ctx, host, logger, index),
+    }
+}
+
+/// Withdraws the sender's approval of a proposal that has not been executed yet.
+#[receive(contract = "ccd_multisig", name = "revoke", parameter="ApproveParameter", return_value = "bool", mutable, enable_logger, error = "Error")]
+pub fn revoke(ctx: &ReceiveContext,host: &mut Host<State>, logger: &mut Logger)-> Result<bool, Error>{
     let param:ApproveParameter = ctx.parameter_cursor().get()?;
-    let index = param.index;
-    if host.state().is_owner(&ctx.sender()){
-        let votes_needed = host.state().voters();
-        let mut proposal = host.state_mut().transactions.get_mut(&index)
-            .expect("The key does not exist");
-        ensure_eq!(index,proposal.index);
-        let approved = proposal.approve(ctx,votes_needed)?;
-        Ok(approved)
-    }else{
-        bail!()
+    ensure!(host.state().is_owner(&ctx.sender()), Error::Unauthorized);
+    let signers = host.state().signers();
+    let threshold = host.state().threshold;
+    let mut proposal = host.state_mut().transactions.get_mut(&param.index)
+        .ok_or(Error::ProposalNotFound)?;
+    let approved = proposal.revoke(ctx,&signers,threshold)?;
+    drop(proposal);
+    if !approved {
+        host.state_mut().release(param.index)?;
     }
-    
+    logger.log(&Event::Revoked { index: param.index, signer: ctx.sender() })?;
+    Ok(approved)
+}
+
+/// Votes against a proposal, it is cancelled once it can no longer be approved.
+#[receive(contract = "ccd_multisig", name = "reject", parameter="ApproveParameter", return_value = "ProposalStatus", mutable, enable_logger, error = "Error")]
+pub fn reject(ctx: &ReceiveContext,host: &mut Host<State>, logger: &mut Logger)-> Result<ProposalStatus, Error>{
+    let param:ApproveParameter = ctx.parameter_cursor().get()?;
+    ensure!(host.state().is_owner(&ctx.sender()), Error::Unauthorized);
+    let signers = host.state().signers();
+    let threshold = host.state().threshold;
+    let mut proposal = host.state_mut().transactions.get_mut(&param.index)
+        .ok_or(Error::ProposalNotFound)?;
+    let status = proposal.reject(ctx,&signers,threshold)?;
+    drop(proposal);
+    logger.log(&Event::Rejected { index: param.index, signer: ctx.sender() })?;
+    if status == ProposalStatus::Cancelled {
+        host.state_mut().release(param.index)?;
+        logger.log(&Event::Cancelled { index: param.index })?;
+    }
+    Ok(status)
+}
+
+/// Lets the creator of a proposal, or a guardian, cancel it before it is executed.
+#[receive(contract = "ccd_multisig", name = "cancel", parameter="ApproveParameter", mutable, enable_logger, error = "Error")]
+pub fn cancel(ctx: &ReceiveContext,host: &mut Host<State>, logger: &mut Logger)-> Result<(), Error>{
+    let param:ApproveParameter = ctx.parameter_cursor().get()?;
+    let guardian = host.state().has_role(&ctx.sender(), Role::Guardian);
+    let mut proposal = host.state_mut().transactions.get_mut(&param.index)
+        .ok_or(Error::ProposalNotFound)?;
+    proposal.cancel(ctx, guardian)?;
+    drop(proposal);
+    host.state_mut().release(param.index)?;
+    logger.log(&Event::Cancelled { index: param.index })?;
+    Ok(())
+}
+
+/// Marks an expired proposal `Expired` and returns the CCD reserved for it
+/// to the available balance. Anybody can call it.
+#[receive(contract = "ccd_multisig", name = "release", parameter="ApproveParameter", mutable, error = "Error")]
+pub fn release(ctx: &ReceiveContext,host: &mut Host<State>)-> Result<(), Error>{
+    let param:ApproveParameter = ctx.parameter_cursor().get()?;
+    let mut proposal = host.state_mut().transactions.get_mut(&param.index)
+        .ok_or(Error::ProposalNotFound)?;
+    ensure!(proposal.status_at(ctx.metadata().slot_time()) == ProposalStatus::Expired, Error::NotExpired);
+    proposal.status = ProposalStatus::Expired;
+    drop(proposal);
+    host.state_mut().release(param.index)
 }
 
-///View function that returns the content of the state.
-#[receive(contract = "ccd_multisig", name = "view",parameter="ApproveParameter",return_value = "Proposal")]
-fn view<'a, 'b>(ctx: &'a ReceiveContext, host: &'b Host<State>) ->   ReceiveResult<Proposal> {
+///View function that returns the content of the state. The status of an
+///open proposal past its expiry is reported as `Expired`.
+#[receive(contract = "ccd_multisig", name = "view",parameter="ApproveParameter",return_value = "Proposal", error = "Error")]
+fn view(ctx: &ReceiveContext, host: &Host<State>) -> Result<Proposal, Error> {
     let param:ApproveParameter = ctx.parameter_cursor().get()?;
-    let prop = host.state().transactions.get(&param.index).unwrap();
-    let mut voted = Vec::new();
-    prop.voted.iter().for_each(|i| voted.push(*i));
-    let (index,amount,receiptient, approvals,fufilled, owner) = (prop.index,prop.amount,prop.receiptient,prop.approvals,prop.fufilled,prop.owner);
-    Ok(Proposal{index,amount,receiptient,voted,approvals,fufilled,owner})
+    let prop = host.state().transactions.get(&param.index).ok_or(Error::ProposalNotFound)?;
+    let mut proposal = prop.clone();
+    proposal.status = prop.status_at(ctx.metadata().slot_time());
+    Ok(proposal)
+}
 
+/// Returns up to `page_size` proposals matching the filters, starting at
+/// `cursor`, with statuses as of now. Pass `next_cursor` back to get the
+/// next page. At most `MAX_PAGE_SIZE` ids are visited per call, so with
+/// filters a page can be short or empty while `next_cursor` is still set.
+#[receive(contract = "ccd_multisig", name = "list_proposals", parameter="ListParameter", return_value = "ProposalPage", error = "Error")]
+fn list_proposals(ctx: &ReceiveContext, host: &Host<State>) -> Result<ProposalPage, Error> {
+    let param: ListParameter = ctx.parameter_cursor().get()?;
+    let now = ctx.metadata().slot_time();
+    let state = host.state();
+    let page_size = match param.page_size {
+        0 => MAX_PAGE_SIZE,
+        page_size => page_size.min(MAX_PAGE_SIZE),
+    } as usize;
+    let end = param.cursor.saturating_add(MAX_PAGE_SIZE).min(state.next_proposal_id);
+    let mut proposals = Vec::new();
+    let mut index = param.cursor;
+    while index < end && proposals.len() < page_size {
+        if let Some(proposal) = state.transactions.get(&index) {
+            let status = proposal.status_at(now);
+            let matches = param.status.map(|wanted| status == wanted).unwrap_or(true)
+                && param.owner.map(|owner| proposal.owner == owner).unwrap_or(true)
+                && param.receiver.map(|receiver| proposal.kind.pays(&receiver)).unwrap_or(true);
+            if matches {
+                proposals.push(ProposalSummary {
+                    index,
+                    status,
+                    owner: proposal.owner,
+                    amount: proposal.kind.outflow().unwrap_or(Amount::zero()),
+                    approvals: proposal.approvals,
+                    expiry: proposal.expiry,
+                });
+            }
+        }
+        index += 1;
+    }
+    let next_cursor = if index < state.next_proposal_id { Some(index) } else { None };
+    Ok(ProposalPage { proposals, next_cursor })
 }
 
-#[receive(contract = "ccd_multisig", name = "get_admins",parameter="ApproveParameter",return_value = "Proposal")]
-fn get_admins<'a, 'b>(_ctx: &'a ReceiveContext, host: &'b Host<State>) ->   ReceiveResult<Vec<Address>> {
-    let admins = host.state().admins.clone();
-    let mut voted = Vec::new();
-    admins.iter().for_each(|admin| voted.push(*admin));
-    Ok(voted)
+/// Returns the current admins.
+#[receive(contract = "ccd_multisig", name = "get_admins",return_value = "Vec<Address>", error = "Error")]
+fn get_admins(_ctx: &ReceiveContext, host: &Host<State>) -> Result<Vec<Address>, Error> {
+    Ok(host.state().admins.clone())
 }
 
-#[receive(contract = "ccd_multisig", name = "get_votes_remaining",parameter="ApproveParameter",return_value = "Proposal")]
-fn get_votes_remaining<'a, 'b>(ctx: &'a ReceiveContext, host: &'b Host<State>) ->   ReceiveResult<u8> {
-    let admins = host.state().admins.clone();
+/// Returns how much more approval weight a proposal needs.
+#[receive(contract = "ccd_multisig", name = "get_votes_remaining",parameter="ApproveParameter",return_value = "u8", error = "Error")]
+fn get_votes_remaining(ctx: &ReceiveContext, host: &Host<State>) -> Result<u8, Error> {
+    let signers = host.state().signers();
+    let threshold = host.state().threshold;
     let param:ApproveParameter = ctx.parameter_cursor().get()?;
-    let proposal = host.state().transactions.get(&param.index).unwrap();
-    Ok(admins.len() as u8 - proposal.approvals)
+    let proposal = host.state().transactions.get(&param.index).ok_or(Error::ProposalNotFound)?;
+    Ok(proposal.votes_remaining(&signers,threshold))
+}
+
+/// Returns every address that holds a role, admins first.
+#[receive(contract = "ccd_multisig", name = "get_roles", return_value = "Vec<(Address, Vec<Role>)>", error = "Error")]
+fn get_roles(_ctx: &ReceiveContext, host: &Host<State>) -> Result<Vec<(Address, Vec<Role>)>, Error> {
+    let state = host.state();
+    let mut roles: Vec<(Address, Vec<Role>)> = state.admins.iter()
+        .map(|admin| (*admin, state.roles_of(admin)))
+        .collect();
+    for (address, _) in state.roles.iter() {
+        if !state.is_owner(&address) {
+            roles.push((*address, state.roles_of(&address)));
+        }
+    }
+    Ok(roles)
+}
+
+/// Returns every admin with its voting weight.
+#[receive(contract = "ccd_multisig", name = "get_weights", return_value = "Vec<(Address, u8)>", error = "Error")]
+fn get_weights(_ctx: &ReceiveContext, host: &Host<State>) -> Result<Vec<(Address, u8)>, Error> {
+    Ok(host.state().signers())
+}
+
+/// Returns the allowance of an admin as of now, if it has one.
+#[receive(contract = "ccd_multisig", name = "get_allowance", parameter="Address", return_value = "Option<Allowance>", error = "Error")]
+fn get_allowance(ctx: &ReceiveContext, host: &Host<State>) -> Result<Option<Allowance>, Error> {
+    let signer: Address = ctx.parameter_cursor().get()?;
+    let now = ctx.metadata().slot_time();
+    Ok(host.state().allowances.get(&signer).map(|allowance| allowance.at(now)))
+}
+
+/// Returns the nonce the next permit of an account must carry.
+#[receive(contract = "ccd_multisig", name = "get_nonce", parameter="AccountAddress", return_value = "u64", error = "Error")]
+fn get_nonce(ctx: &ReceiveContext, host: &Host<State>) -> Result<u64, Error> {
+    let account: AccountAddress = ctx.parameter_cursor().get()?;
+    Ok(host.state().nonces.get(&account).map(|nonce| *nonce).unwrap_or(0))
+}
+
+/// Returns the CCD deposited by everyone and the number of deposits.
+#[receive(contract = "ccd_multisig", name = "get_total_deposits", return_value = "(Amount, u64)", error = "Error")]
+fn get_total_deposits(_ctx: &ReceiveContext, host: &Host<State>) -> Result<(Amount, u64), Error> {
+    Ok((host.state().total_deposits, host.state().deposit_count))
+}
+
+/// Returns the CCD an address deposited.
+#[receive(contract = "ccd_multisig", name = "get_deposits_of", parameter = "Address", return_value = "Amount", error = "Error")]
+fn get_deposits_of(ctx: &ReceiveContext, host: &Host<State>) -> Result<Amount, Error> {
+    let depositor: Address = ctx.parameter_cursor().get()?;
+    Ok(host.state().deposit_totals.get(&depositor).map(|total| *total).unwrap_or(Amount::zero()))
+}
+
+/// Returns the deposit with the given sequence number.
+#[receive(contract = "ccd_multisig", name = "get_deposit", parameter = "u64", return_value = "Deposit", error = "Error")]
+fn get_deposit(ctx: &ReceiveContext, host: &Host<State>) -> Result<Deposit, Error> {
+    let index: u64 = ctx.parameter_cursor().get()?;
+    let deposit = host.state().deposits.get(&index).ok_or(Error::DepositNotFound)?;
+    Ok(deposit.clone())
+}
+
+/// Returns whether the contract is paused.
+#[receive(contract = "ccd_multisig", name = "is_paused", return_value = "bool", error = "Error")]
+fn is_paused(_ctx: &ReceiveContext, host: &Host<State>) -> Result<bool, Error> {
+    Ok(host.state().paused)
+}
+
+/// Returns the layout version of the contract state.
+#[receive(contract = "ccd_multisig", name = "get_version", return_value = "u32", error = "Error")]
+fn get_version(_ctx: &ReceiveContext, host: &Host<State>) -> Result<u32, Error> {
+    Ok(host.state().version)
+}
+
+/// Returns the index the next created proposal will get.
+#[receive(contract = "ccd_multisig", name = "get_next_proposal_id", return_value = "u32", error = "Error")]
+fn get_next_proposal_id(_ctx: &ReceiveContext, host: &Host<State>) -> Result<u32, Error> {
+    Ok(host.state().next_proposal_id)
+}
+
+/// Returns the approval weight proposals currently need.
+#[receive(contract = "ccd_multisig", name = "get_threshold", return_value = "u8", error = "Error")]
+fn get_threshold(_ctx: &ReceiveContext, host: &Host<State>) -> Result<u8, Error> {
+    Ok(host.state().threshold)
+}
+
+/// Returns the CCD balance with what is committed to approved proposals and
+/// what is available for new ones.
+#[receive(contract = "ccd_multisig", name = "get_balance", return_value = "BalanceSummary", error = "Error")]
+fn get_balance(_ctx: &ReceiveContext, host: &Host<State>) -> Result<BalanceSummary, Error> {
+    let balance = host.self_balance();
+    let committed = host.state().committed;
+    let available = host.state().available(balance);
+    Ok(BalanceSummary { balance, committed, available })
+}
+
+/// Returns how much of a CIS-2 token the contract holds.
+#[receive(contract = "ccd_multisig", name = "get_token_balance", parameter = "TokenBalanceParameter", return_value = "TokenAmountU256", error = "Error")]
+fn get_token_balance(ctx: &ReceiveContext, host: &Host<State>) -> Result<TokenAmountU256, Error> {
+    let param: TokenBalanceParameter = ctx.parameter_cursor().get()?;
+    let key = (param.token, param.token_id);
+    Ok(host.state().token_balances.get(&key).map(|balance| *balance).unwrap_or_default())
 }
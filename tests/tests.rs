@@ -1,6 +1,10 @@
 use concordium_smart_contract_testing::*;
 use ccd_multisig::*;
 use std::str::FromStr;
+use concordium_std::Serial;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use concordium_cis2::{AdditionalData, OnReceivingCis2Params, TokenAmountU256, TokenAmountU64, TokenIdVec};
 
 
 /// A test account.
@@ -12,14 +16,25 @@ const MIKE: AccountAddress = AccountAddress([2u8; 32]);
 const MIKE_ADDR: Address = Address::Account(MIKE);
 const SETH: AccountAddress = AccountAddress([3u8; 32]);
 const SETH_ADDR: Address = Address::Account(SETH);
+/// An admin whose account keys are known, so tests can sign permits for it.
+const DAVE: AccountAddress = AccountAddress([4u8; 32]);
+const DAVE_ADDR: Address = Address::Account(DAVE);
+/// The secret key of `DAVE`.
+const DAVE_KEY: [u8; 32] = [7u8; 32];
 
 
 /// The initial balance of the ALICE test account.
-const ACC_INITIAL_BALANCE: Amount = Amount::from_ccd(100000_000);
+const ACC_INITIAL_BALANCE: Amount = Amount::from_ccd(100_000_000);
+
+/// How long proposals stay open in the test contracts.
+const MAX_LIFETIME: Duration = Duration::from_days(7);
 
 /// A [`Signer`] with one set of keys, used for signing transactions.
 const SIGNER: Signer = Signer::with_one_key();
 
+/// A later module with a different state layout, built from `tests/upgrade-target`.
+const UPGRADE_TARGET: &str = "./tests/upgrade-target/concordium-out/module.wasm.v1";
+
 
 
 
@@ -43,9 +58,10 @@ fn test_can_send_ccd_to_contract() {
 fn test_creating_a_transaction(){
     let (mut chain, init) = initialize();
     let param = TxParameter{
-        index: 0,
         amount:Amount::from_ccd(100_000),
-        receiver: BOB
+        receiver: BOB,
+        deadline: None,
+        auto_execute: false
     };
     // Update the contract via the `receive` entrypoint with the parameter `false`.
     chain
@@ -58,24 +74,1255 @@ fn test_creating_a_transaction(){
         .unwrap();
     
 }
+#[test]
+fn test_proposal_ids_are_assigned_sequentially(){
+    let (mut chain, init) = initialize();
+    assert_eq!(create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(1)), 0);
+    assert_eq!(create_tx(&mut chain, &init, BOB, SETH, Amount::from_ccd(2)), 1);
+    let next = invoke(&chain, &init, "get_next_proposal_id", &())
+        .parse_return_value::<u32>();
+    assert_eq!(next, Ok(2));
+    assert_eq!(view(&chain, &init, 1).kind, ProposalKind::Transfer{receiver: SETH, amount: Amount::from_ccd(2)});
+}
+
+#[test]
+fn test_only_admins_and_proposers_can_create_proposals(){
+    let (mut chain, init) = initialize();
+    let param = TxParameter::new(BOB, 100);
+    let err = update(&mut chain, &init, SETH, "create_tx", &param, Amount::zero())
+        .expect_err("Create proposal as outsider");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Unauthorized));
+
+    let (mut chain, init) = initialize_with(InitParameter{
+        admins: vec![ALICE_ADDR, BOB_ADDR],
+        weights: Vec::new(),
+        threshold: 2,
+        roles: vec![(SETH_ADDR, vec![Role::Proposer])],
+        max_lifetime: MAX_LIFETIME,
+        execution_delay: Duration::from_millis(0),
+        auto_execute: false,
+    });
+    assert_eq!(create_tx(&mut chain, &init, SETH, BOB, Amount::from_ccd(1)), 0);
+    let err = update(&mut chain, &init, MIKE, "create_tx", &param, Amount::zero())
+        .expect_err("Create proposal as outsider");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Unauthorized));
+}
+
+#[test]
+fn test_revoke_approval(){
+    let (mut chain, init) = initialize_with_threshold(2);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let index = create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(100));
+    let err = update(&mut chain, &init, ALICE, "revoke", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Revoke without voting");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::NotVoted));
+    for signer in [ALICE, BOB] {
+        update(&mut chain, &init, signer, "approve", &ApproveParameter::new(index), Amount::zero())
+            .expect("Approve proposal");
+    }
+    let still_approved = update(&mut chain, &init, BOB, "revoke", &ApproveParameter::new(index), Amount::zero())
+        .expect("Revoke approval")
+        .parse_return_value::<bool>();
+    assert_eq!(still_approved, Ok(false));
+    let proposal = view(&chain, &init, index);
+    assert_eq!(proposal.status, ProposalStatus::Pending);
+    assert_eq!(proposal.voted, vec![ALICE_ADDR]);
+    assert_eq!(proposal.approvals, 1);
+
+    update(&mut chain, &init, MIKE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    update(&mut chain, &init, MIKE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect("Execute proposal");
+    let err = update(&mut chain, &init, ALICE, "revoke", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Revoke after execution");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::AlreadyExecuted));
+}
+
+#[test]
+fn test_rejections_cancel_unreachable_proposal(){
+    let (mut chain, init) = initialize_with_threshold(2);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let index = create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(100));
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    let err = update(&mut chain, &init, ALICE, "reject", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Reject after approving");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::AlreadyVoted));
+    let status = update(&mut chain, &init, BOB, "reject", &ApproveParameter::new(index), Amount::zero())
+        .expect("First rejection")
+        .parse_return_value::<ProposalStatus>();
+    assert_eq!(status, Ok(ProposalStatus::Pending));
+    let status = update(&mut chain, &init, MIKE, "reject", &ApproveParameter::new(index), Amount::zero())
+        .expect("Second rejection")
+        .parse_return_value::<ProposalStatus>();
+    assert_eq!(status, Ok(ProposalStatus::Cancelled));
+    assert_eq!(view(&chain, &init, index).rejections, vec![BOB_ADDR, MIKE_ADDR]);
+    let err = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Execute cancelled proposal");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::ProposalCancelled));
+}
+
+#[test]
+fn test_owner_can_cancel_proposal(){
+    let (mut chain, init) = initialize_with_threshold(1);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let index = create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(100));
+    update(&mut chain, &init, BOB, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    let err = update(&mut chain, &init, BOB, "cancel", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Cancel someone else's proposal");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Unauthorized));
+    update(&mut chain, &init, ALICE, "cancel", &ApproveParameter::new(index), Amount::zero())
+        .expect("Cancel own proposal");
+    assert_eq!(view(&chain, &init, index).status, ProposalStatus::Cancelled);
+    let err = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Execute cancelled proposal");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::ProposalCancelled));
+}
+
+#[test]
+fn test_proposal_expires_after_deadline(){
+    let (mut chain, init) = initialize_with_threshold(1);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let index = create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(100));
+    assert_eq!(view(&chain, &init, index).expiry, Timestamp::from_timestamp_millis(MAX_LIFETIME.millis()));
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    chain.tick_block_time(Duration::from_days(8)).unwrap();
+    assert_eq!(view(&chain, &init, index).status, ProposalStatus::Expired);
+    let err = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Execute expired proposal");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::ProposalExpired));
+    let err = update(&mut chain, &init, BOB, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Approve expired proposal");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::ProposalExpired));
+}
+
+#[test]
+fn test_deadline_must_be_within_max_lifetime(){
+    let (mut chain, init) = initialize();
+    chain.tick_block_time(Duration::from_days(1)).unwrap();
+    let now = chain.block_time();
+    let mut param = TxParameter::new(SETH, 100);
+    param.deadline = Some(now.checked_add(Duration::from_days(8)).unwrap());
+    let err = update(&mut chain, &init, ALICE, "create_tx", &param, Amount::zero())
+        .expect_err("Deadline past the maximum lifetime");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::InvalidDeadline));
+    param.deadline = Some(now);
+    let err = update(&mut chain, &init, ALICE, "create_tx", &param, Amount::zero())
+        .expect_err("Deadline in the past");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::InvalidDeadline));
+    let deadline = now.checked_add(Duration::from_hours(1)).unwrap();
+    param.deadline = Some(deadline);
+    update(&mut chain, &init, ALICE, "create_tx", &param, Amount::zero())
+        .expect("Deadline within the maximum lifetime");
+    assert_eq!(view(&chain, &init, 0).expiry, deadline);
+}
+
+#[test]
+fn test_execution_waits_for_timelock(){
+    let (mut chain, init) = initialize_with(InitParameter{
+        admins: vec![ALICE_ADDR, BOB_ADDR, MIKE_ADDR],
+        weights: Vec::new(),
+        threshold: 2,
+        roles: Vec::new(),
+        max_lifetime: MAX_LIFETIME,
+        execution_delay: Duration::from_days(1),
+        auto_execute: false,
+    });
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let index = create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(100));
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    assert_eq!(view(&chain, &init, index).executable_at, None);
+    chain.tick_block_time(Duration::from_hours(1)).unwrap();
+    update(&mut chain, &init, BOB, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    let executable_at = chain.block_time().checked_add(Duration::from_days(1)).unwrap();
+    assert_eq!(view(&chain, &init, index).executable_at, Some(executable_at));
+    let err = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Execute during timelock");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::TimelockActive));
+    chain.tick_block_time(Duration::from_days(1)).unwrap();
+    update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect("Execute after timelock");
+}
+
+#[test]
+fn test_governance_adds_signer_and_changes_threshold(){
+    let (mut chain, init) = initialize_with_threshold(2);
+    let index = propose(&mut chain, &init, ALICE, ProposalKind::AddSigner(SETH_ADDR));
+    approve_and_execute(&mut chain, &init, index, &[ALICE, BOB]);
+    let admins: Vec<Address> = invoke(&chain, &init, "get_admins", &ApproveParameter::new(0))
+        .parse_return_value()
+        .expect("Admins");
+    assert_eq!(admins, vec![ALICE_ADDR, BOB_ADDR, MIKE_ADDR, SETH_ADDR]);
+
+    let index = propose(&mut chain, &init, SETH, ProposalKind::ChangeThreshold(3));
+    approve_and_execute(&mut chain, &init, index, &[SETH, MIKE]);
+    let threshold = invoke(&chain, &init, "get_threshold", &()).parse_return_value::<u8>();
+    assert_eq!(threshold, Ok(3));
+
+    let param = ProposeParameter{kind: ProposalKind::ChangeThreshold(5), deadline: None, auto_execute: false};
+    let err = update(&mut chain, &init, ALICE, "propose", &param, Amount::zero())
+        .expect_err("Threshold above the number of signers");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::InvalidThreshold));
+}
+
+#[test]
+fn test_governance_replaces_and_removes_signers(){
+    let (mut chain, init) = initialize_with_threshold(2);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let payment = create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(100));
+    update(&mut chain, &init, MIKE, "approve", &ApproveParameter::new(payment), Amount::zero())
+        .expect("Approve proposal");
+
+    let kind = ProposalKind::ReplaceSigner{old: MIKE_ADDR, new: SETH_ADDR};
+    let index = propose(&mut chain, &init, ALICE, kind);
+    approve_and_execute(&mut chain, &init, index, &[ALICE, BOB]);
+    let err = update(&mut chain, &init, MIKE, "approve", &ApproveParameter::new(payment), Amount::zero())
+        .expect_err("Approve as replaced signer");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Unauthorized));
+
+    // Mike's earlier approval no longer counts towards the threshold.
+    update(&mut chain, &init, SETH, "approve", &ApproveParameter::new(payment), Amount::zero())
+        .expect("Approve proposal");
+    let err = update(&mut chain, &init, SETH, "transfer", &ApproveParameter::new(payment), Amount::zero())
+        .expect_err("Execute with a stale approval");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::NotApproved));
+
+    let index = propose(&mut chain, &init, ALICE, ProposalKind::RemoveSigner(SETH_ADDR));
+    approve_and_execute(&mut chain, &init, index, &[ALICE, BOB]);
+    let param = ProposeParameter{kind: ProposalKind::RemoveSigner(BOB_ADDR), deadline: None, auto_execute: false};
+    let err = update(&mut chain, &init, ALICE, "propose", &param, Amount::zero())
+        .expect_err("Fewer signers than the threshold");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::InvalidThreshold));
+}
+
+#[test]
+fn test_state_changes_are_logged(){
+    let (mut chain, init) = initialize_with_threshold(1);
+    let deposit = update(&mut chain, &init, SETH, "insert", &(), Amount::from_ccd(1_000))
+        .expect("Funding contract");
+    assert_eq!(events(&deposit), vec![Event::Deposit{sender: SETH_ADDR, amount: Amount::from_ccd(1_000)}]);
+
+    let created = update(&mut chain, &init, ALICE, "create_tx", &TxParameter::new(SETH, 100), Amount::zero())
+        .expect("Creating proposal");
+    assert_eq!(events(&created), vec![Event::ProposalCreated{index: 0, owner: ALICE_ADDR}]);
+    let approved = update(&mut chain, &init, BOB, "approve", &ApproveParameter::new(0), Amount::zero())
+        .expect("Approve proposal");
+    assert_eq!(events(&approved), vec![Event::Approved{index: 0, signer: BOB_ADDR}]);
+    let executed = update(&mut chain, &init, BOB, "transfer", &ApproveParameter::new(0), Amount::zero())
+        .expect("Execute proposal");
+    assert_eq!(events(&executed), vec![Event::Executed{index: 0}]);
+
+    let index = propose(&mut chain, &init, ALICE, ProposalKind::ReplaceSigner{old: MIKE_ADDR, new: SETH_ADDR});
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    let executed = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect("Execute proposal");
+    assert_eq!(events(&executed), vec![
+        Event::SignerRemoved{signer: MIKE_ADDR},
+        Event::SignerAdded{signer: SETH_ADDR},
+        Event::Executed{index},
+    ]);
+}
+
+#[test]
+fn test_errors_are_typed(){
+    let (mut chain, init) = initialize_with_threshold(1);
+    for entrypoint in ["approve", "revoke", "reject", "cancel", "transfer"] {
+        let err = update(&mut chain, &init, ALICE, entrypoint, &ApproveParameter::new(7), Amount::zero())
+            .expect_err("Missing proposal");
+        assert_eq!(err.parse_return_value::<Error>(), Ok(Error::ProposalNotFound), "{}", entrypoint);
+    }
+    for entrypoint in ["view", "get_votes_remaining"] {
+        let err = chain.contract_invoke(ALICE, ALICE_ADDR, Energy::from(100_000), UpdateContractPayload {
+            address:      init.contract_address,
+            amount:       Amount::zero(),
+            receive_name: OwnedReceiveName::new_unchecked(format!("ccd_multisig.{}", entrypoint)),
+            message:      OwnedParameter::from_serial(&ApproveParameter::new(7)).unwrap()
+        }).expect_err("Missing proposal");
+        assert_eq!(err.parse_return_value::<Error>(), Ok(Error::ProposalNotFound), "{}", entrypoint);
+    }
+
+    let index = create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(100));
+    let err = update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Approve without funds");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Overcommitted));
+    let err = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Execute without approval");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::NotApproved));
+    fund(&mut chain, &init, Amount::from_ccd(100));
+    approve_and_execute(&mut chain, &init, index, &[ALICE]);
+}
+
+#[test]
+fn test_batch_transfer_pays_every_leg(){
+    let (mut chain, init) = initialize_with_threshold(2);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let legs = vec![
+        TransferLeg{receiver: BOB, amount: Amount::from_ccd(100)},
+        TransferLeg{receiver: SETH, amount: Amount::from_ccd(200)},
+    ];
+    let index = propose(&mut chain, &init, ALICE, ProposalKind::BatchTransfer(legs));
+    approve_and_execute(&mut chain, &init, index, &[ALICE, MIKE]);
+    assert_eq!(chain.contract_balance(init.contract_address), Some(Amount::from_ccd(700)));
+    assert_eq!(chain.account_balance_available(SETH), Some(ACC_INITIAL_BALANCE + Amount::from_ccd(200)));
+
+    let param = ProposeParameter{kind: ProposalKind::BatchTransfer(Vec::new()), deadline: None, auto_execute: false};
+    let err = update(&mut chain, &init, ALICE, "propose", &param, Amount::zero())
+        .expect_err("Empty batch");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::InvalidBatch));
+}
+
+#[test]
+fn test_batch_transfer_is_atomic(){
+    let (mut chain, init) = initialize_with_threshold(1);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let too_large = vec![
+        TransferLeg{receiver: BOB, amount: Amount::from_ccd(600)},
+        TransferLeg{receiver: SETH, amount: Amount::from_ccd(600)},
+    ];
+    let index = propose(&mut chain, &init, ALICE, ProposalKind::BatchTransfer(too_large));
+    let err = update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Batch above the balance");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Overcommitted));
+
+    let missing_leg = vec![
+        TransferLeg{receiver: BOB, amount: Amount::from_ccd(100)},
+        TransferLeg{receiver: AccountAddress([9u8; 32]), amount: Amount::from_ccd(100)},
+    ];
+    let index = propose(&mut chain, &init, ALICE, ProposalKind::BatchTransfer(missing_leg));
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    let err = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Batch with a missing account");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::TransferFailed));
+    assert_eq!(chain.contract_balance(init.contract_address), Some(Amount::from_ccd(1_000)));
+    assert_eq!(view(&chain, &init, index).status, ProposalStatus::Approved);
+}
+
+#[test]
+fn test_contract_call_proposal_records_outcome(){
+    let (mut chain, init) = initialize_with_threshold(1);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    // A second multisig administered by the first one.
+    let target = chain
+        .contract_init(SIGNER, ALICE, Energy::from(10_000), InitContractPayload {
+            amount:    Amount::zero(),
+            mod_ref:   chain.get_contract(init.contract_address).unwrap().module_reference,
+            init_name: OwnedContractName::new_unchecked("init_ccd_multisig".to_string()),
+            param:     OwnedParameter::from_serial(&InitParameter{
+                admins: vec![Address::Contract(init.contract_address)],
+                weights: Vec::new(),
+                threshold: 1,
+                roles: Vec::new(),
+                max_lifetime: MAX_LIFETIME,
+                execution_delay: Duration::from_millis(0),
+                auto_execute: false,
+            }).unwrap(),
+        })
+        .expect("Initializing target contract")
+        .contract_address;
+
+    let kind = ProposalKind::ContractCall{
+        address: target,
+        entrypoint: OwnedEntrypointName::new_unchecked("create_tx".to_string()),
+        parameter: OwnedParameter::from_serial(&TxParameter::new(SETH, 100)).unwrap(),
+        amount: Amount::zero(),
+    };
+    let index = propose(&mut chain, &init, ALICE, kind);
+    approve_and_execute(&mut chain, &init, index, &[BOB]);
+    let proposal = view(&chain, &init, index);
+    assert_eq!(proposal.status, ProposalStatus::Executed);
+    assert_eq!(proposal.call_outcome, Some(CallOutcome{success: true, return_value: to_bytes(&0u32)}));
+
+    let kind = ProposalKind::ContractCall{
+        address: target,
+        entrypoint: OwnedEntrypointName::new_unchecked("approve".to_string()),
+        parameter: OwnedParameter::from_serial(&ApproveParameter::new(5)).unwrap(),
+        amount: Amount::zero(),
+    };
+    let index = propose(&mut chain, &init, ALICE, kind);
+    approve_and_execute(&mut chain, &init, index, &[BOB]);
+    let proposal = view(&chain, &init, index);
+    assert_eq!(proposal.status, ProposalStatus::Failed);
+    assert_eq!(proposal.call_outcome, Some(CallOutcome{success: false, return_value: to_bytes(&Error::ProposalNotFound)}));
+
+    let kind = ProposalKind::ContractCall{
+        address: target,
+        entrypoint: OwnedEntrypointName::new_unchecked("insert".to_string()),
+        parameter: OwnedParameter::empty(),
+        amount: Amount::from_ccd(250),
+    };
+    let index = propose(&mut chain, &init, ALICE, kind);
+    approve_and_execute(&mut chain, &init, index, &[BOB]);
+    assert_eq!(chain.contract_balance(target), Some(Amount::from_ccd(250)));
+    assert_eq!(chain.contract_balance(init.contract_address), Some(Amount::from_ccd(750)));
+}
+
+#[test]
+fn test_cis2_tokens_are_tracked_and_sent(){
+    let (mut chain, init) = initialize_with_threshold(1);
+    // A second multisig stands in for the token contract calling the hook.
+    let token_init = chain
+        .contract_init(SIGNER, ALICE, Energy::from(10_000), InitContractPayload {
+            amount:    Amount::zero(),
+            mod_ref:   chain.get_contract(init.contract_address).unwrap().module_reference,
+            init_name: OwnedContractName::new_unchecked("init_ccd_multisig".to_string()),
+            param:     OwnedParameter::from_serial(&InitParameter{
+                admins: vec![ALICE_ADDR],
+                weights: Vec::new(),
+                threshold: 1,
+                roles: Vec::new(),
+                max_lifetime: MAX_LIFETIME,
+                execution_delay: Duration::from_millis(0),
+                auto_execute: false,
+            }).unwrap(),
+        })
+        .expect("Initializing token contract");
+    let token = token_init.contract_address;
+    let token_id = TokenIdVec(vec![7]);
+    let received = OnReceivingCis2Params{
+        token_id: token_id.clone(),
+        amount: tokens(500),
+        from: BOB_ADDR,
+        data: AdditionalData::empty(),
+    };
+
+    let err = update(&mut chain, &init, ALICE, "onReceivingCIS2", &received, Amount::zero())
+        .expect_err("Hook called by an account");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Unauthorized));
+
+    let kind = ProposalKind::ContractCall{
+        address: init.contract_address,
+        entrypoint: OwnedEntrypointName::new_unchecked("onReceivingCIS2".to_string()),
+        parameter: OwnedParameter::from_serial(&received).unwrap(),
+        amount: Amount::zero(),
+    };
+    let index = propose(&mut chain, &token_init, ALICE, kind);
+    approve_and_execute(&mut chain, &token_init, index, &[ALICE]);
+    let balance_param = TokenBalanceParameter{token, token_id: token_id.clone()};
+    let balance = invoke(&chain, &init, "get_token_balance", &balance_param)
+        .parse_return_value::<TokenAmountU256>();
+    assert_eq!(balance, Ok(tokens(500)));
+
+    let kind = ProposalKind::TokenTransfer{token, token_id: token_id.clone(), amount: tokens(501), receiver: SETH};
+    let index = propose(&mut chain, &init, ALICE, kind);
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    let err = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Send more tokens than held");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::InsufficientFunds));
+
+    // The stand-in token contract has no CIS-2 `transfer`, so the call is refused.
+    let kind = ProposalKind::TokenTransfer{token, token_id, amount: tokens(200), receiver: SETH};
+    let index = propose(&mut chain, &init, ALICE, kind);
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    let err = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Token contract refuses transfer");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::TokenTransferFailed));
+    let balance = invoke(&chain, &init, "get_token_balance", &balance_param)
+        .parse_return_value::<TokenAmountU256>();
+    assert_eq!(balance, Ok(tokens(500)));
+}
+
+#[test]
+fn test_weighted_votes(){
+    // Alice is a founder worth three votes, 3 of the 5 total weight approve.
+    let (mut chain, init) = initialize_with(InitParameter{
+        admins: vec![ALICE_ADDR, BOB_ADDR, MIKE_ADDR],
+        weights: vec![(ALICE_ADDR, 3)],
+        threshold: 3,
+        roles: Vec::new(),
+        max_lifetime: MAX_LIFETIME,
+        execution_delay: Duration::from_millis(0),
+        auto_execute: false,
+    });
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let weights = invoke(&chain, &init, "get_weights", &())
+        .parse_return_value::<Vec<(Address, u8)>>();
+    assert_eq!(weights, Ok(vec![(ALICE_ADDR, 3), (BOB_ADDR, 1), (MIKE_ADDR, 1)]));
+
+    let index = create_tx(&mut chain, &init, BOB, SETH, Amount::from_ccd(100));
+    update(&mut chain, &init, BOB, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    let remaining = invoke(&chain, &init, "get_votes_remaining", &ApproveParameter::new(index))
+        .parse_return_value::<u8>();
+    assert_eq!(remaining, Ok(2));
+    let approved = update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal")
+        .parse_return_value::<bool>();
+    assert_eq!(approved, Ok(true));
+    assert_eq!(view(&chain, &init, index).approvals, 4);
+
+    // Bob and Mike alone cannot outvote Alice, nor remove her.
+    let index = create_tx(&mut chain, &init, BOB, SETH, Amount::from_ccd(100));
+    let status = update(&mut chain, &init, ALICE, "reject", &ApproveParameter::new(index), Amount::zero())
+        .expect("Reject proposal")
+        .parse_return_value::<ProposalStatus>();
+    assert_eq!(status, Ok(ProposalStatus::Cancelled));
+    let param = ProposeParameter{kind: ProposalKind::RemoveSigner(ALICE_ADDR), deadline: None, auto_execute: false};
+    let err = update(&mut chain, &init, BOB, "propose", &param, Amount::zero())
+        .expect_err("Remove signer below threshold");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::InvalidThreshold));
+
+    let index = propose(&mut chain, &init, ALICE, ProposalKind::ChangeWeight{signer: BOB_ADDR, weight: 2});
+    approve_and_execute(&mut chain, &init, index, &[ALICE]);
+    let weights = invoke(&chain, &init, "get_weights", &())
+        .parse_return_value::<Vec<(Address, u8)>>();
+    assert_eq!(weights, Ok(vec![(ALICE_ADDR, 3), (BOB_ADDR, 2), (MIKE_ADDR, 1)]));
+    let index = create_tx(&mut chain, &init, BOB, SETH, Amount::from_ccd(100));
+    for signer in [BOB, MIKE] {
+        update(&mut chain, &init, signer, "approve", &ApproveParameter::new(index), Amount::zero())
+            .expect("Approve proposal");
+    }
+    update(&mut chain, &init, MIKE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect("Execute proposal");
+
+    let err = update(&mut chain, &init, ALICE, "propose",
+        &ProposeParameter{kind: ProposalKind::ChangeWeight{signer: MIKE_ADDR, weight: 0}, deadline: None, auto_execute: false},
+        Amount::zero())
+        .expect_err("Zero weight");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::InvalidWeight));
+}
+
+#[test]
+fn test_roles_restrict_entrypoints(){
+    let (mut chain, init) = initialize_with(InitParameter{
+        admins: vec![ALICE_ADDR, BOB_ADDR],
+        weights: Vec::new(),
+        threshold: 1,
+        roles: vec![(SETH_ADDR, vec![Role::Executor])],
+        max_lifetime: MAX_LIFETIME,
+        execution_delay: Duration::from_millis(0),
+        auto_execute: false,
+    });
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let roles = invoke(&chain, &init, "get_roles", &())
+        .parse_return_value::<Vec<(Address, Vec<Role>)>>();
+    assert_eq!(roles, Ok(vec![
+        (ALICE_ADDR, vec![Role::Proposer, Role::Approver, Role::Executor]),
+        (BOB_ADDR, vec![Role::Proposer, Role::Approver, Role::Executor]),
+        (SETH_ADDR, vec![Role::Executor]),
+    ]));
+
+    // The executor bot can run approved payments but neither propose nor vote.
+    let index = create_tx(&mut chain, &init, ALICE, MIKE, Amount::from_ccd(100));
+    for entrypoint in ["approve", "create_tx"] {
+        let err = update(&mut chain, &init, SETH, entrypoint, &TxParameter::new(MIKE, 100), Amount::zero())
+            .expect_err("Executor proposing or voting");
+        assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Unauthorized), "{}", entrypoint);
+    }
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    let err = update(&mut chain, &init, MIKE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Execute without the executor role");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Unauthorized));
+    update(&mut chain, &init, SETH, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect("Execute as executor");
+
+    // A guardian may cancel any proposal.
+    let kind = ProposalKind::GrantRole{address: MIKE_ADDR, role: Role::Guardian};
+    let index = propose(&mut chain, &init, ALICE, kind);
+    approve_and_execute(&mut chain, &init, index, &[ALICE]);
+    let index = create_tx(&mut chain, &init, BOB, SETH, Amount::from_ccd(100));
+    update(&mut chain, &init, MIKE, "cancel", &ApproveParameter::new(index), Amount::zero())
+        .expect("Cancel as guardian");
+    assert_eq!(view(&chain, &init, index).status, ProposalStatus::Cancelled);
+
+    let param = ProposeParameter{kind: ProposalKind::GrantRole{address: SETH_ADDR, role: Role::Approver}, deadline: None, auto_execute: false};
+    let err = update(&mut chain, &init, ALICE, "propose", &param, Amount::zero())
+        .expect_err("Grant approver role");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::InvalidRole));
+    let index = propose(&mut chain, &init, ALICE, ProposalKind::RevokeRole{address: SETH_ADDR, role: Role::Executor});
+    approve_and_execute(&mut chain, &init, index, &[ALICE]);
+    let roles = invoke(&chain, &init, "get_roles", &())
+        .parse_return_value::<Vec<(Address, Vec<Role>)>>()
+        .expect("Roles");
+    assert_eq!(roles.last(), Some(&(MIKE_ADDR, vec![Role::Guardian])));
+}
+
+#[test]
+fn test_rotated_out_signers_lose_their_roles(){
+    let (mut chain, init) = initialize_with(InitParameter{
+        admins: vec![ALICE_ADDR, BOB_ADDR, MIKE_ADDR],
+        weights: Vec::new(),
+        threshold: 1,
+        roles: vec![(BOB_ADDR, vec![Role::Guardian]), (MIKE_ADDR, vec![Role::Guardian])],
+        max_lifetime: MAX_LIFETIME,
+        execution_delay: Duration::from_millis(0),
+        auto_execute: false,
+    });
+    let index = propose(&mut chain, &init, ALICE, ProposalKind::ReplaceSigner{old: BOB_ADDR, new: SETH_ADDR});
+    approve_and_execute(&mut chain, &init, index, &[ALICE]);
+    let index = propose(&mut chain, &init, ALICE, ProposalKind::RemoveSigner(MIKE_ADDR));
+    approve_and_execute(&mut chain, &init, index, &[ALICE]);
+    let roles = invoke(&chain, &init, "get_roles", &())
+        .parse_return_value::<Vec<(Address, Vec<Role>)>>();
+    assert_eq!(roles, Ok(vec![
+        (ALICE_ADDR, vec![Role::Proposer, Role::Approver, Role::Executor]),
+        (SETH_ADDR, vec![Role::Proposer, Role::Approver, Role::Executor, Role::Guardian]),
+    ]));
+    for old in [BOB, MIKE] {
+        let err = update(&mut chain, &init, old, "pause", &(), Amount::zero())
+            .expect_err("Pause with a rotated-out key");
+        assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Unauthorized));
+    }
+}
+
+#[test]
+fn test_permit_applies_signed_approvals(){
+    let (mut chain, init) = initialize_with(InitParameter{
+        admins: vec![ALICE_ADDR, BOB_ADDR, DAVE_ADDR],
+        weights: Vec::new(),
+        threshold: 2,
+        roles: Vec::new(),
+        max_lifetime: MAX_LIFETIME,
+        execution_delay: Duration::from_millis(0),
+        auto_execute: false,
+    });
+    create_account_with_key(&mut chain, DAVE, DAVE_KEY);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let index = create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(100));
+    let message = PermitMessage{
+        contract_address: init.contract_address,
+        nonce: 0,
+        timestamp: chain.block_time().checked_add(Duration::from_hours(1)).unwrap(),
+        index,
+    };
+
+    let expired = chain.block_time();
+    chain.tick_block_time(Duration::from_millis(1)).unwrap();
+    let rejected = [
+        (sign_permit(DAVE, [8u8; 32], message.clone()), Error::InvalidSignature),
+        (sign_permit(DAVE, DAVE_KEY, PermitMessage{nonce: 1, ..message.clone()}), Error::NonceMismatch),
+        (sign_permit(DAVE, DAVE_KEY, PermitMessage{timestamp: expired, ..message.clone()}), Error::PermitExpired),
+        (sign_permit(DAVE, DAVE_KEY, PermitMessage{contract_address: ContractAddress::new(9, 0), ..message.clone()}), Error::WrongContract),
+    ];
+    for (permit, error) in rejected {
+        let err = update(&mut chain, &init, SETH, "permit", &PermitParameter{permits: vec![permit]}, Amount::zero())
+            .expect_err("Invalid permit");
+        assert_eq!(err.parse_return_value::<Error>(), Ok(error));
+    }
+
+    // A relayer without any role submits the signed approval.
+    let param = PermitParameter{permits: vec![sign_permit(DAVE, DAVE_KEY, message.clone())]};
+    let approved = update(&mut chain, &init, SETH, "permit", &param, Amount::zero())
+        .expect("Apply permit")
+        .parse_return_value::<Vec<bool>>();
+    assert_eq!(approved, Ok(vec![false]));
+    assert_eq!(view(&chain, &init, index).voted, vec![DAVE_ADDR]);
+    let nonce = invoke(&chain, &init, "get_nonce", &DAVE).parse_return_value::<u64>();
+    assert_eq!(nonce, Ok(1));
+    let err = update(&mut chain, &init, SETH, "permit", &param, Amount::zero())
+        .expect_err("Replay permit");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::NonceMismatch));
+
+    approve_and_execute(&mut chain, &init, index, &[BOB]);
+    assert_eq!(view(&chain, &init, index).status, ProposalStatus::Executed);
+}
+
+#[test]
+fn test_execute_signed_bundle(){
+    let (mut chain, init) = initialize_with(InitParameter{
+        admins: vec![ALICE_ADDR, BOB_ADDR, DAVE_ADDR],
+        weights: Vec::new(),
+        threshold: 2,
+        roles: Vec::new(),
+        max_lifetime: MAX_LIFETIME,
+        execution_delay: Duration::from_millis(0),
+        auto_execute: false,
+    });
+    let bob_key = [9u8; 32];
+    create_account_with_key(&mut chain, BOB, bob_key);
+    create_account_with_key(&mut chain, DAVE, DAVE_KEY);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let message = SignedProposal{
+        contract_address: init.contract_address,
+        index: 0,
+        timestamp: chain.block_time().checked_add(Duration::from_hours(1)).unwrap(),
+        kind: ProposalKind::Transfer{receiver: SETH, amount: Amount::from_ccd(100)},
+    };
+
+    let param = ExecuteSignedParameter{message: message.clone(), signatures: vec![(DAVE, sign(DAVE, DAVE_KEY, &message))]};
+    let err = update(&mut chain, &init, ALICE, "execute_signed", &param, Amount::zero())
+        .expect_err("Too few signatures");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::NotApproved));
+
+    let param = ExecuteSignedParameter{message: message.clone(), signatures: vec![
+        (DAVE, sign(DAVE, DAVE_KEY, &message)),
+        (BOB, sign(BOB, bob_key, &message)),
+    ]};
+    let err = update(&mut chain, &init, SETH, "execute_signed", &param, Amount::zero())
+        .expect_err("Submit without the executor role");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Unauthorized));
+    let status = update(&mut chain, &init, ALICE, "execute_signed", &param, Amount::zero())
+        .expect("Execute signed proposal")
+        .parse_return_value::<ProposalStatus>();
+    assert_eq!(status, Ok(ProposalStatus::Executed));
+    assert_eq!(chain.contract_balance(init.contract_address), Some(Amount::from_ccd(900)));
+    let proposal = view(&chain, &init, 0);
+    assert_eq!(proposal.owner, DAVE_ADDR);
+    assert_eq!(proposal.voted, vec![DAVE_ADDR, BOB_ADDR]);
+
+    let err = update(&mut chain, &init, ALICE, "execute_signed", &param, Amount::zero())
+        .expect_err("Replay signed proposal");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::WrongProposalIndex));
+
+    let message = SignedProposal{index: 1, ..message};
+    let param = ExecuteSignedParameter{message: message.clone(), signatures: Vec::new()};
+    let err = update(&mut chain, &init, ALICE, "execute_signed", &param, Amount::zero())
+        .expect_err("No signatures");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::NoSignatures));
+    chain.tick_block_time(Duration::from_hours(2)).unwrap();
+    let param = ExecuteSignedParameter{message: message.clone(), signatures: vec![
+        (DAVE, sign(DAVE, DAVE_KEY, &message)),
+        (BOB, sign(BOB, bob_key, &message)),
+    ]};
+    let err = update(&mut chain, &init, ALICE, "execute_signed", &param, Amount::zero())
+        .expect_err("Expired signatures");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::SignaturesExpired));
+}
+
+#[test]
+fn test_final_approval_executes_flagged_proposals(){
+    let (mut chain, init) = initialize_with_threshold(2);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let param = TxParameter{auto_execute: true, ..TxParameter::new(SETH, 100_000_000)};
+    let index: u32 = update(&mut chain, &init, ALICE, "create_tx", &param, Amount::zero())
+        .expect("Creating proposal")
+        .parse_return_value()
+        .expect("Proposal index");
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    let approved = update(&mut chain, &init, BOB, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Final approval");
+    assert_eq!(events(&approved), vec![Event::Approved{index, signer: BOB_ADDR}, Event::Executed{index}]);
+    assert_eq!(view(&chain, &init, index).status, ProposalStatus::Executed);
+    assert_eq!(chain.contract_balance(init.contract_address), Some(Amount::from_ccd(900)));
+
+    // Without enough funds the final approval is refused.
+    let param = TxParameter{auto_execute: true, ..TxParameter::new(SETH, 5_000_000_000)};
+    let index: u32 = update(&mut chain, &init, ALICE, "create_tx", &param, Amount::zero())
+        .expect("Creating proposal")
+        .parse_return_value()
+        .expect("Proposal index");
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    let err = update(&mut chain, &init, BOB, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Final approval above the balance");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Overcommitted));
+    assert_eq!(view(&chain, &init, index).status, ProposalStatus::Pending);
+
+    // A contract-wide flag still respects the timelock.
+    let (mut chain, init) = initialize_with(InitParameter{
+        admins: vec![ALICE_ADDR, BOB_ADDR],
+        weights: Vec::new(),
+        threshold: 1,
+        roles: Vec::new(),
+        max_lifetime: MAX_LIFETIME,
+        execution_delay: Duration::from_days(1),
+        auto_execute: true,
+    });
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let index = create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(100));
+    assert!(view(&chain, &init, index).auto_execute);
+    update(&mut chain, &init, BOB, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    assert_eq!(view(&chain, &init, index).status, ProposalStatus::Approved);
+}
+
+#[test]
+fn test_final_approval_stands_when_execution_cannot_go_through(){
+    let (mut chain, init) = initialize_with(InitParameter{
+        admins: vec![ALICE_ADDR, BOB_ADDR],
+        weights: Vec::new(),
+        threshold: 2,
+        roles: Vec::new(),
+        max_lifetime: MAX_LIFETIME,
+        execution_delay: Duration::from_millis(0),
+        auto_execute: true,
+    });
+    let first = propose(&mut chain, &init, ALICE, ProposalKind::AddSigner(SETH_ADDR));
+    let second = propose(&mut chain, &init, ALICE, ProposalKind::AddSigner(SETH_ADDR));
+    for signer in [ALICE, BOB] {
+        update(&mut chain, &init, signer, "approve", &ApproveParameter::new(first), Amount::zero())
+            .expect("Approve proposal");
+    }
+    assert_eq!(view(&chain, &init, first).status, ProposalStatus::Executed);
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(second), Amount::zero())
+        .expect("Approve proposal");
+    let approved = update(&mut chain, &init, BOB, "approve", &ApproveParameter::new(second), Amount::zero())
+        .expect("Final approval of a stale proposal");
+    assert_eq!(events(&approved), vec![Event::Approved{index: second, signer: BOB_ADDR}]);
+    assert_eq!(view(&chain, &init, second).status, ProposalStatus::Approved);
+
+    let kind = ProposalKind::TokenTransfer{
+        token: ContractAddress::new(99, 0),
+        token_id: TokenIdVec(vec![1]),
+        amount: tokens(10),
+        receiver: SETH,
+    };
+    let index = propose(&mut chain, &init, ALICE, kind);
+    for signer in [ALICE, BOB] {
+        update(&mut chain, &init, signer, "approve", &ApproveParameter::new(index), Amount::zero())
+            .expect("Approve token transfer without tokens");
+    }
+    assert_eq!(view(&chain, &init, index).status, ProposalStatus::Approved);
+
+    // A batch with a missing account fails as a whole and pays nobody.
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let legs = vec![
+        TransferLeg{receiver: MIKE, amount: Amount::from_ccd(100)},
+        TransferLeg{receiver: AccountAddress([9u8; 32]), amount: Amount::from_ccd(100)},
+    ];
+    let index = propose(&mut chain, &init, ALICE, ProposalKind::BatchTransfer(legs));
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    let approved = update(&mut chain, &init, BOB, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Final approval of a failing batch");
+    assert_eq!(events(&approved), vec![Event::Approved{index, signer: BOB_ADDR}]);
+    let proposal = view(&chain, &init, index);
+    assert_eq!(proposal.status, ProposalStatus::Approved);
+    assert_eq!(proposal.voted, vec![ALICE_ADDR, BOB_ADDR]);
+    assert_eq!(proposal.reserved, Amount::from_ccd(200));
+    assert_eq!(chain.contract_balance(init.contract_address), Some(Amount::from_ccd(1_000)));
+    let err = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Execute failing batch");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::TransferFailed));
+}
+
+#[test]
+fn test_spend_within_allowance(){
+    let (mut chain, init) = initialize_with_threshold(2);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let spend = SpendParameter{receiver: SETH, amount: Amount::from_ccd(60)};
+    let err = update(&mut chain, &init, ALICE, "spend", &spend, Amount::zero())
+        .expect_err("Spend without allowance");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Unauthorized));
+
+    let kind = ProposalKind::SetAllowance{signer: ALICE_ADDR, limit: Amount::from_ccd(100), window: Duration::from_days(1)};
+    let index = propose(&mut chain, &init, ALICE, kind);
+    approve_and_execute(&mut chain, &init, index, &[ALICE, BOB]);
+    let spent = update(&mut chain, &init, ALICE, "spend", &spend, Amount::zero())
+        .expect("Spend within allowance");
+    assert_eq!(events(&spent), vec![Event::Spent{signer: ALICE_ADDR, receiver: SETH, amount: Amount::from_ccd(60)}]);
+    let err = update(&mut chain, &init, ALICE, "spend", &spend, Amount::zero())
+        .expect_err("Spend beyond allowance");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::AllowanceExceeded));
+    let err = update(&mut chain, &init, BOB, "spend", &spend, Amount::zero())
+        .expect_err("Spend another signer's allowance");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Unauthorized));
+    let allowance = invoke(&chain, &init, "get_allowance", &ALICE_ADDR)
+        .parse_return_value::<Option<Allowance>>()
+        .expect("Allowance")
+        .expect("Alice has an allowance");
+    assert_eq!(allowance.remaining(), Amount::from_ccd(40));
+
+    // The next window starts with the full allowance again.
+    chain.tick_block_time(Duration::from_days(1)).unwrap();
+    update(&mut chain, &init, ALICE, "spend", &spend, Amount::zero())
+        .expect("Spend in the next window");
+    assert_eq!(chain.contract_balance(init.contract_address), Some(Amount::from_ccd(880)));
+
+    let kind = ProposalKind::SetAllowance{signer: ALICE_ADDR, limit: Amount::zero(), window: Duration::from_days(1)};
+    let index = propose(&mut chain, &init, ALICE, kind);
+    approve_and_execute(&mut chain, &init, index, &[ALICE, BOB]);
+    let allowance = invoke(&chain, &init, "get_allowance", &ALICE_ADDR)
+        .parse_return_value::<Option<Allowance>>();
+    assert_eq!(allowance, Ok(None));
+}
+
+#[test]
+fn test_list_proposals_pages_and_filters(){
+    let (mut chain, init) = initialize_with_threshold(2);
+    for (sender, receiver) in [(ALICE, SETH), (BOB, MIKE), (ALICE, MIKE), (BOB, SETH), (ALICE, SETH)] {
+        create_tx(&mut chain, &init, sender, receiver, Amount::from_ccd(10));
+    }
+    update(&mut chain, &init, ALICE, "cancel", &ApproveParameter::new(2), Amount::zero())
+        .expect("Cancel proposal");
+    let list = |chain: &Chain, param: &ListParameter| -> (Vec<u32>, Option<u32>) {
+        let page = invoke(chain, &init, "list_proposals", param)
+            .parse_return_value::<ProposalPage>()
+            .expect("Page");
+        (page.proposals.iter().map(|proposal| proposal.index).collect(), page.next_cursor)
+    };
+
+    let mut param = ListParameter{cursor: 0, page_size: 2, status: None, owner: None, receiver: None};
+    assert_eq!(list(&chain, &param), (vec![0, 1], Some(2)));
+    param.cursor = 2;
+    assert_eq!(list(&chain, &param), (vec![2, 3], Some(4)));
+    param.cursor = 4;
+    assert_eq!(list(&chain, &param), (vec![4], None));
+
+    let param = ListParameter{cursor: 0, page_size: 10, status: Some(ProposalStatus::Pending), owner: Some(ALICE_ADDR), receiver: None};
+    assert_eq!(list(&chain, &param), (vec![0, 4], None));
+    let param = ListParameter{cursor: 0, page_size: 10, status: None, owner: None, receiver: Some(MIKE)};
+    assert_eq!(list(&chain, &param), (vec![1, 2], None));
+
+    chain.tick_block_time(MAX_LIFETIME).unwrap();
+    chain.tick_block_time(Duration::from_millis(1)).unwrap();
+    let param = ListParameter{cursor: 0, page_size: 10, status: Some(ProposalStatus::Expired), owner: None, receiver: None};
+    let page = invoke(&chain, &init, "list_proposals", &param)
+        .parse_return_value::<ProposalPage>()
+        .expect("Page");
+    assert_eq!(page.proposals.len(), 4);
+    assert_eq!(page.proposals[0], ProposalSummary{
+        index: 0,
+        status: ProposalStatus::Expired,
+        owner: ALICE_ADDR,
+        amount: Amount::from_ccd(10),
+        approvals: 0,
+        expiry: view(&chain, &init, 0).expiry,
+    });
+}
+
+#[test]
+fn test_list_proposals_bounds_the_scan(){
+    let (mut chain, init) = initialize_with_threshold(2);
+    for _ in 0..MAX_PAGE_SIZE {
+        create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(10));
+    }
+    let last = create_tx(&mut chain, &init, BOB, MIKE, Amount::from_ccd(10));
+    let list = |chain: &Chain, param: &ListParameter| -> ProposalPage {
+        invoke(chain, &init, "list_proposals", param)
+            .parse_return_value::<ProposalPage>()
+            .expect("Page")
+    };
+
+    let mut param = ListParameter{cursor: 0, page_size: 10, status: None, owner: Some(BOB_ADDR), receiver: None};
+    let page = list(&chain, &param);
+    assert!(page.proposals.is_empty());
+    assert_eq!(page.next_cursor, Some(MAX_PAGE_SIZE));
+    param.cursor = MAX_PAGE_SIZE;
+    let page = list(&chain, &param);
+    assert_eq!(page.proposals.iter().map(|proposal| proposal.index).collect::<Vec<_>>(), vec![last]);
+    assert_eq!(page.next_cursor, None);
+
+    // A page size of zero means the largest page.
+    let param = ListParameter{cursor: 0, page_size: 0, status: None, owner: None, receiver: None};
+    let page = list(&chain, &param);
+    assert_eq!(page.proposals.len(), MAX_PAGE_SIZE as usize);
+    assert_eq!(page.next_cursor, Some(MAX_PAGE_SIZE));
+}
+
+#[test]
+fn test_upgrade_proposal_runs_migration(){
+    let (mut chain, init) = initialize_with_threshold(2);
+    let module = chain.get_contract(init.contract_address).unwrap().module_reference;
+    let version = invoke(&chain, &init, "get_version", &()).parse_return_value::<u32>();
+    assert_eq!(version, Ok(STATE_VERSION));
+
+    let kind = ProposalKind::Upgrade{module: ModuleReference::from([7u8; 32]), migration: None};
+    let index = propose(&mut chain, &init, ALICE, kind);
+    for signer in [ALICE, BOB] {
+        update(&mut chain, &init, signer, "approve", &ApproveParameter::new(index), Amount::zero())
+            .expect("Approve proposal");
+    }
+    let err = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Upgrade through transfer");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::WrongEntrypoint));
+    let err = update(&mut chain, &init, ALICE, "upgrade", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Upgrade to a missing module");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::UpgradeFailed));
+
+    let migration = (OwnedEntrypointName::new_unchecked("get_nothing".to_string()), OwnedParameter::empty());
+    let kind = ProposalKind::Upgrade{module, migration: Some(migration)};
+    let index = propose(&mut chain, &init, ALICE, kind);
+    for signer in [ALICE, BOB] {
+        update(&mut chain, &init, signer, "approve", &ApproveParameter::new(index), Amount::zero())
+            .expect("Approve proposal");
+    }
+    let err = update(&mut chain, &init, ALICE, "upgrade", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Upgrade with a failing migration");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::MigrationFailed));
+
+    let migration = (OwnedEntrypointName::new_unchecked("migrate".to_string()), OwnedParameter::empty());
+    let kind = ProposalKind::Upgrade{module, migration: Some(migration)};
+    let index = propose(&mut chain, &init, ALICE, kind);
+    for signer in [ALICE, BOB] {
+        update(&mut chain, &init, signer, "approve", &ApproveParameter::new(index), Amount::zero())
+            .expect("Approve proposal");
+    }
+    let upgraded = update(&mut chain, &init, ALICE, "upgrade", &ApproveParameter::new(index), Amount::zero())
+        .expect("Upgrade contract");
+    assert_eq!(events(&upgraded), vec![Event::Upgraded{module}, Event::Executed{index}]);
+    assert_eq!(view(&chain, &init, index).status, ProposalStatus::Executed);
+
+    let err = update(&mut chain, &init, ALICE, "migrate", &(), Amount::zero())
+        .expect_err("Migrate from outside");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Unauthorized));
+}
+
+#[test]
+fn test_upgrade_migrates_to_a_new_state_layout(){
+    let (mut chain, init) = initialize_with_threshold(2);
+    let module = chain
+        .module_deploy_v1(SIGNER, ALICE, module_load_v1(UPGRADE_TARGET).expect("Module exists at path"))
+        .expect("Deploying module")
+        .module_reference;
+    let migration = (OwnedEntrypointName::new_unchecked("migrate".to_string()), OwnedParameter::empty());
+    let kind = ProposalKind::Upgrade{module, migration: Some(migration)};
+    let index = propose(&mut chain, &init, ALICE, kind);
+    for signer in [ALICE, BOB] {
+        update(&mut chain, &init, signer, "approve", &ApproveParameter::new(index), Amount::zero())
+            .expect("Approve proposal");
+    }
+    let upgraded = update(&mut chain, &init, BOB, "upgrade", &ApproveParameter::new(index), Amount::zero())
+        .expect("Upgrade to a new layout");
+    assert_eq!(events(&upgraded), vec![Event::Upgraded{module}, Event::Executed{index}]);
+    assert_eq!(chain.get_contract(init.contract_address).unwrap().module_reference, module);
+    let version = invoke(&chain, &init, "get_version", &()).parse_return_value::<(u32, u32)>();
+    assert_eq!(version, Ok((STATE_VERSION + 1, STATE_VERSION)));
+}
+
+#[test]
+fn test_pausing_key_is_rotated_out_while_paused(){
+    let (mut chain, init) = initialize_with(InitParameter{
+        admins: vec![ALICE_ADDR, BOB_ADDR, MIKE_ADDR],
+        weights: Vec::new(),
+        threshold: 2,
+        roles: vec![(MIKE_ADDR, vec![Role::Guardian])],
+        max_lifetime: MAX_LIFETIME,
+        execution_delay: Duration::from_millis(0),
+        auto_execute: false,
+    });
+    // Mike's key leaked: it pauses and tries to keep the contract paused.
+    update(&mut chain, &init, MIKE, "pause", &(), Amount::zero())
+        .expect("Pause with the leaked key");
+    let unpause = propose(&mut chain, &init, ALICE, ProposalKind::Unpause);
+    let err = update(&mut chain, &init, MIKE, "cancel", &ApproveParameter::new(unpause), Amount::zero())
+        .expect_err("Guardian cancels unpause");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Unauthorized));
+    let param = ProposeParameter{kind: ProposalKind::AddSigner(SETH_ADDR), deadline: None, auto_execute: false};
+    let err = update(&mut chain, &init, ALICE, "propose", &param, Amount::zero())
+        .expect_err("Add signer while paused");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Paused));
+
+    let index = propose(&mut chain, &init, ALICE, ProposalKind::ReplaceSigner{old: MIKE_ADDR, new: SETH_ADDR});
+    approve_and_execute(&mut chain, &init, index, &[ALICE, BOB]);
+    let index = propose(&mut chain, &init, ALICE, ProposalKind::RevokeRole{address: SETH_ADDR, role: Role::Guardian});
+    approve_and_execute(&mut chain, &init, index, &[ALICE, BOB]);
+    assert_eq!(invoke(&chain, &init, "is_paused", &()).parse_return_value::<bool>(), Ok(true));
+    approve_and_execute(&mut chain, &init, unpause, &[ALICE, BOB]);
+    let err = update(&mut chain, &init, MIKE, "pause", &(), Amount::zero())
+        .expect_err("Pause with the rotated-out key");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Unauthorized));
+}
+
+#[test]
+fn test_pause_blocks_until_unpause_proposal(){
+    let (mut chain, init) = initialize_with(InitParameter{
+        admins: vec![ALICE_ADDR, BOB_ADDR, MIKE_ADDR],
+        weights: Vec::new(),
+        threshold: 2,
+        roles: vec![(SETH_ADDR, vec![Role::Guardian])],
+        max_lifetime: MAX_LIFETIME,
+        execution_delay: Duration::from_millis(0),
+        auto_execute: false,
+    });
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let index = create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(100));
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+
+    let paused = update(&mut chain, &init, SETH, "pause", &(), Amount::zero())
+        .expect("Pause as guardian");
+    assert_eq!(events(&paused), vec![Event::Paused{by: SETH_ADDR}]);
+    assert_eq!(invoke(&chain, &init, "is_paused", &()).parse_return_value::<bool>(), Ok(true));
+    for (entrypoint, param) in [
+        ("create_tx", to_bytes(&TxParameter::new(SETH, 100))),
+        ("approve", to_bytes(&ApproveParameter::new(index))),
+        ("transfer", to_bytes(&ApproveParameter::new(index))),
+    ] {
+        let err = chain.contract_update(SIGNER, BOB, BOB_ADDR, Energy::from(100_000), UpdateContractPayload {
+            address:      init.contract_address,
+            amount:       Amount::zero(),
+            receive_name: OwnedReceiveName::new_unchecked(format!("ccd_multisig.{}", entrypoint)),
+            message:      OwnedParameter::try_from(param).unwrap(),
+        }).expect_err("Blocked while paused");
+        assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Paused), "{}", entrypoint);
+    }
+
+    // Only a full multisig proposal lifts the pause.
+    let unpause = propose(&mut chain, &init, ALICE, ProposalKind::Unpause);
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(unpause), Amount::zero())
+        .expect("Approve unpause");
+    let err = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(unpause), Amount::zero())
+        .expect_err("Unpause with one approval");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::NotApproved));
+    approve_and_execute(&mut chain, &init, unpause, &[BOB]);
+    assert_eq!(invoke(&chain, &init, "is_paused", &()).parse_return_value::<bool>(), Ok(false));
+
+    approve_and_execute(&mut chain, &init, index, &[BOB]);
+    assert_eq!(view(&chain, &init, index).status, ProposalStatus::Executed);
+    let param = ProposeParameter{kind: ProposalKind::Unpause, deadline: None, auto_execute: false};
+    let err = update(&mut chain, &init, ALICE, "propose", &param, Amount::zero())
+        .expect_err("Unpause while not paused");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::NotPaused));
+    update(&mut chain, &init, MIKE, "pause", &(), Amount::zero())
+        .expect("Pause as admin");
+}
+
+#[test]
+fn test_deposits_are_recorded(){
+    let (mut chain, init) = initialize_with_threshold(1);
+    fund(&mut chain, &init, Amount::from_ccd(100));
+    let memo = Some("payroll top-up".to_string());
+    update(&mut chain, &init, SETH, "insert", &memo, Amount::from_ccd(50))
+        .expect("Deposit with memo");
+    fund(&mut chain, &init, Amount::from_ccd(25));
+
+    let deposit: Deposit = invoke(&chain, &init, "get_deposit", &1u64)
+        .parse_return_value()
+        .expect("Deposit");
+    assert_eq!(deposit.sender, SETH_ADDR);
+    assert_eq!(deposit.amount, Amount::from_ccd(50));
+    assert_eq!(deposit.memo, memo);
+    let totals: (Amount, u64) = invoke(&chain, &init, "get_total_deposits", &())
+        .parse_return_value()
+        .expect("Total deposits");
+    assert_eq!(totals, (Amount::from_ccd(175), 3));
+    let alice: Amount = invoke(&chain, &init, "get_deposits_of", &ALICE_ADDR)
+        .parse_return_value()
+        .expect("Deposits of Alice");
+    assert_eq!(alice, Amount::from_ccd(125));
+    let mike: Amount = invoke(&chain, &init, "get_deposits_of", &MIKE_ADDR)
+        .parse_return_value()
+        .expect("Deposits of Mike");
+    assert_eq!(mike, Amount::zero());
+
+    let memo = Some("x".repeat(MAX_MEMO_LENGTH + 1));
+    let err = update(&mut chain, &init, SETH, "insert", &memo, Amount::from_ccd(1))
+        .expect_err("Memo too long");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::MemoTooLong));
+    let err = update(&mut chain, &init, SETH, "insert", &Some("spam".to_string()), Amount::zero())
+        .expect_err("Empty deposit");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::EmptyDeposit));
+}
+
+#[test]
+fn test_approved_proposals_reserve_the_balance(){
+    let (mut chain, init) = initialize_with_threshold(1);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let first = create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(600));
+    let second = create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(600));
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(first), Amount::zero())
+        .expect("Approve proposal");
+    assert_eq!(view(&chain, &init, first).reserved, Amount::from_ccd(600));
+    let balance: BalanceSummary = invoke(&chain, &init, "get_balance", &())
+        .parse_return_value()
+        .expect("Balance");
+    assert_eq!(balance, BalanceSummary{
+        balance: Amount::from_ccd(1_000),
+        committed: Amount::from_ccd(600),
+        available: Amount::from_ccd(400),
+    });
+    let err = update(&mut chain, &init, BOB, "approve", &ApproveParameter::new(second), Amount::zero())
+        .expect_err("Overcommitting the balance");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Overcommitted));
+
+    // Allowances cannot spend committed CCD either.
+    let kind = ProposalKind::SetAllowance{signer: ALICE_ADDR, limit: Amount::from_ccd(1_000), window: Duration::from_days(1)};
+    let index = propose(&mut chain, &init, ALICE, kind);
+    approve_and_execute(&mut chain, &init, index, &[ALICE]);
+    let spend = SpendParameter{receiver: SETH, amount: Amount::from_ccd(500)};
+    let err = update(&mut chain, &init, ALICE, "spend", &spend, Amount::zero())
+        .expect_err("Spend committed CCD");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::InsufficientFunds));
+
+    // Revoking releases the reservation, executing consumes it.
+    update(&mut chain, &init, ALICE, "revoke", &ApproveParameter::new(first), Amount::zero())
+        .expect("Revoke approval");
+    approve_and_execute(&mut chain, &init, second, &[BOB]);
+    let balance: BalanceSummary = invoke(&chain, &init, "get_balance", &())
+        .parse_return_value()
+        .expect("Balance");
+    assert_eq!(balance.committed, Amount::zero());
+    assert_eq!(balance.available, Amount::from_ccd(400));
+
+    // Cancelled and expired proposals give their reservation back.
+    let index = create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(300));
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    update(&mut chain, &init, ALICE, "cancel", &ApproveParameter::new(index), Amount::zero())
+        .expect("Cancel proposal");
+    let index = create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(300));
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    let err = update(&mut chain, &init, SETH, "release", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Release open proposal");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::NotExpired));
+    chain.tick_block_time(Duration::from_days(8)).unwrap();
+    update(&mut chain, &init, SETH, "release", &ApproveParameter::new(index), Amount::zero())
+        .expect("Release expired proposal");
+    let balance: BalanceSummary = invoke(&chain, &init, "get_balance", &())
+        .parse_return_value()
+        .expect("Balance");
+    assert_eq!(balance.committed, Amount::zero());
+}
+
 #[test]
 fn test_is_address(){
     let address = Address::from_str("45FWHaAQz44w5VrcrX7XUeNHGwTvPHWRZGSUsdekqyw44Tz2iu").unwrap();
-    assert_eq!(address.is_account(), true);
+    assert!(address.is_account());
 }
 
 #[test]
 fn test_is_acc_address(){
     let address = AccountAddress::from_str("45FWHaAQz44w5VrcrX7XUeNHGwTvPHWRZGSUsdekqyw44Tz2iu").unwrap();
     println!("{}", address)
-    //assert_eq!(address.is_account(), true);
+    //assert!(address.is_account());
 }
 
 
 
 #[test]
 fn test_view_proposal(){
+    let (mut chain, init) = initialize_with_threshold(2);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let index = create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(100));
+    assert_eq!(view(&chain, &init, index).status, ProposalStatus::Pending);
+    for signer in [ALICE, BOB] {
+        update(&mut chain, &init, signer, "approve", &ApproveParameter::new(index), Amount::zero())
+            .expect("Approve proposal");
+    }
+    let proposal = view(&chain, &init, index);
+    assert_eq!(proposal.status, ProposalStatus::Approved);
+    assert_eq!(proposal.voted, vec![ALICE_ADDR, BOB_ADDR]);
+    let status = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect("Execute proposal")
+        .parse_return_value::<ProposalStatus>();
+    assert_eq!(status, Ok(ProposalStatus::Executed));
+    assert_eq!(view(&chain, &init, index).status, ProposalStatus::Executed);
 
+    let err = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Execute twice");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::AlreadyExecuted));
+    let err = update(&mut chain, &init, MIKE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Approve executed proposal");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::AlreadyExecuted));
+}
+
+#[test]
+fn test_failed_transfer_marks_proposal_failed(){
+    let (mut chain, init) = initialize_with_threshold(1);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    let missing = AccountAddress([9u8; 32]);
+    let index = create_tx(&mut chain, &init, ALICE, missing, Amount::from_ccd(100));
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(index), Amount::zero())
+        .expect("Approve proposal");
+    let status = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect("Execute proposal")
+        .parse_return_value::<ProposalStatus>();
+    assert_eq!(status, Ok(ProposalStatus::Failed));
+    assert_eq!(chain.contract_balance(init.contract_address), Some(Amount::from_ccd(1_000)));
+    let err = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect_err("Retry failed proposal");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::ProposalFailed));
 }
 
 
@@ -83,9 +1330,10 @@ fn test_view_proposal(){
 fn test_approving_a_transaction(){
     let (mut chain, init) = initialize();
     let param = TxParameter{
-        index: 0,
         amount:Amount::from_ccd(100_000),
-        receiver: BOB
+        receiver: BOB,
+        deadline: None,
+        auto_execute: false
     };
     // Update the contract via the `receive` entrypoint with the parameter `false`.
     chain
@@ -96,7 +1344,7 @@ fn test_approving_a_transaction(){
             message:      OwnedParameter::from_serial(&param).unwrap()
         })
         .unwrap();
-    let app_tx = ApproveParameter::new(1);
+    let app_tx = ApproveParameter::new(0);
     chain
         .contract_update(SIGNER, ALICE, ALICE_ADDR, Energy::from(100_000), UpdateContractPayload {
             address:      init.contract_address,
@@ -110,27 +1358,109 @@ fn test_approving_a_transaction(){
 
 #[test]
 fn test_should_fail_if_none_admin_tries_to_approve_transaction(){
-    todo!()    
+    let (mut chain, init) = initialize();
+    create_tx(&mut chain, &init, ALICE, BOB, Amount::from_ccd(100));
+    let admins: Vec<Address> = invoke(&chain, &init, "get_admins", &ApproveParameter::new(0))
+        .parse_return_value()
+        .expect("Admins");
+    assert!(!admins.contains(&SETH_ADDR));
+    let err = update(&mut chain, &init, SETH, "approve", &ApproveParameter::new(0), Amount::zero())
+        .expect_err("Approve as outsider");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::Unauthorized));
 }
 
 
 #[test]
 fn test_excute_transaction_if_approved(){
-    todo!()
-
+    let (mut chain, init) = initialize();
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(100));
+    for signer in [ALICE, BOB, MIKE] {
+        update(&mut chain, &init, signer, "approve", &ApproveParameter::new(0), Amount::zero())
+            .expect("Approve proposal");
+    }
+    update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(0), Amount::zero())
+        .expect("Execute proposal");
+    assert_eq!(chain.contract_balance(init.contract_address), Some(Amount::from_ccd(900)));
+    assert_eq!(chain.account_balance_available(SETH), Some(ACC_INITIAL_BALANCE + Amount::from_ccd(100)));
 }
 
 #[test]
 fn test_should_fail_if_transaction_is_not_approved(){
-    todo!()
-
+    let (mut chain, init) = initialize();
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(100));
+    for signer in [ALICE, BOB] {
+        update(&mut chain, &init, signer, "approve", &ApproveParameter::new(0), Amount::zero())
+            .expect("Approve proposal");
+    }
+    let err = update(&mut chain, &init, ALICE, "transfer", &ApproveParameter::new(0), Amount::zero())
+        .expect_err("Execute unapproved proposal");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::NotApproved));
 }
 
 
 
 #[test]
 fn test_should_fail_if_admin_tries_to_approve_twice(){
-    todo!()
+    let (mut chain, init) = initialize();
+    create_tx(&mut chain, &init, ALICE, BOB, Amount::from_ccd(100));
+    update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(0), Amount::zero())
+        .expect("First approval");
+    let err = update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(0), Amount::zero())
+        .expect_err("Second approval");
+    assert_eq!(err.parse_return_value::<Error>(), Ok(Error::AlreadyVoted));
+}
+
+#[test]
+fn test_threshold_of_two_out_of_three(){
+    let (mut chain, init) = initialize_with_threshold(2);
+    fund(&mut chain, &init, Amount::from_ccd(1_000));
+    create_tx(&mut chain, &init, ALICE, SETH, Amount::from_ccd(100));
+    let first = update(&mut chain, &init, ALICE, "approve", &ApproveParameter::new(0), Amount::zero())
+        .expect("First approval");
+    assert_eq!(first.parse_return_value::<bool>(), Ok(false));
+    let remaining = invoke(&chain, &init, "get_votes_remaining", &ApproveParameter::new(0))
+        .parse_return_value::<u8>();
+    assert_eq!(remaining, Ok(1));
+    let second = update(&mut chain, &init, MIKE, "approve", &ApproveParameter::new(0), Amount::zero())
+        .expect("Second approval");
+    assert_eq!(second.parse_return_value::<bool>(), Ok(true));
+    update(&mut chain, &init, BOB, "transfer", &ApproveParameter::new(0), Amount::zero())
+        .expect("Execute proposal");
+    assert_eq!(chain.contract_balance(init.contract_address), Some(Amount::from_ccd(900)));
+}
+
+#[test]
+fn test_init_rejects_invalid_threshold(){
+    for threshold in [0, 4] {
+        let (mut chain, mod_ref) = deploy();
+        let param = InitParameter{admins: vec![ALICE_ADDR, BOB_ADDR, MIKE_ADDR], weights: Vec::new(), threshold, roles: Vec::new(), max_lifetime: MAX_LIFETIME, execution_delay: Duration::from_millis(0), auto_execute: false};
+        let res = chain.contract_init(SIGNER, ALICE, Energy::from(10_000), InitContractPayload {
+            amount:    Amount::zero(),
+            mod_ref,
+            init_name: OwnedContractName::new_unchecked("init_ccd_multisig".to_string()),
+            param:     OwnedParameter::from_serial(&param).unwrap(),
+        });
+        assert!(res.is_err());
+    }
+}
+
+#[test]
+fn test_init_rejects_duplicate_signers(){
+    let (mut chain, mod_ref) = deploy();
+    let param = InitParameter{admins: vec![ALICE_ADDR, ALICE_ADDR, BOB_ADDR], weights: Vec::new(), threshold: 2, roles: Vec::new(), max_lifetime: MAX_LIFETIME, execution_delay: Duration::from_millis(0), auto_execute: false};
+    let err = chain.contract_init(SIGNER, ALICE, Energy::from(10_000), InitContractPayload {
+        amount:    Amount::zero(),
+        mod_ref,
+        init_name: OwnedContractName::new_unchecked("init_ccd_multisig".to_string()),
+        param:     OwnedParameter::from_serial(&param).unwrap(),
+    }).expect_err("Duplicate signer");
+    // Init rejections carry no return value, only the reject reason.
+    let ContractInitErrorKind::ExecutionError{error: InitExecutionError::Reject{reason, ..}} = err.kind else {
+        panic!("Expected a rejection");
+    };
+    assert_eq!(reason, concordium_std::Reject::from(Error::SignerAlreadyExists).error_code.get());
 }
 
 
@@ -156,9 +1486,41 @@ fn test_should_fail_if_admin_tries_to_approve_twice(){
 /// Does the following:
 ///  - Creates the [`Chain`]
 ///  - Creates one account, `Alice` with `10_000` CCD as the initial balance.
-///  - Initializes the contract.
+///  - Initializes the contract with `Alice`, `Bob` and `Mike` as admins, all of
+///    whom must approve a proposal.
 ///  - Returns the [`Chain`] and the [`ContractInitSuccess`]
 fn initialize() -> (Chain, ContractInitSuccess) {
+    initialize_with_threshold(3)
+}
+
+/// Same as [`initialize`] but with a custom approval threshold.
+fn initialize_with_threshold(threshold: u8) -> (Chain, ContractInitSuccess) {
+    let mut param = InitParameter{admins:Vec::new(), weights: Vec::new(), threshold, roles: Vec::new(), max_lifetime: MAX_LIFETIME, execution_delay: Duration::from_millis(0), auto_execute: false};
+    param.admins.push(ALICE_ADDR);
+    param.admins.push(BOB_ADDR);
+    param.admins.push(MIKE_ADDR);
+    initialize_with(param)
+}
+
+/// Initializes the contract with the given parameter.
+fn initialize_with(param: InitParameter) -> (Chain, ContractInitSuccess) {
+    let (mut chain, mod_ref) = deploy();
+
+    // Initialize the contract.
+    let init = chain
+        .contract_init(SIGNER, ALICE, Energy::from(10_000), InitContractPayload {
+            amount:    Amount::zero(),
+            mod_ref,
+            init_name: OwnedContractName::new_unchecked("init_ccd_multisig".to_string()),
+            param:     OwnedParameter::from_serial(&param).unwrap(),
+        })
+        .expect("Initializing contract");
+
+    (chain, init)
+}
+
+/// Creates the [`Chain`] with the test accounts and deploys the module.
+fn deploy() -> (Chain, ModuleReference) {
     // Initialize the test chain.
     let mut chain = Chain::new();
 
@@ -168,27 +1530,144 @@ fn initialize() -> (Chain, ContractInitSuccess) {
     chain.create_account(Account::new(MIKE, ACC_INITIAL_BALANCE));
     chain.create_account(Account::new(SETH, ACC_INITIAL_BALANCE));
 
-
-
-
     // Load the module.
     let module = module_load_v1("./concordium-out/module.wasm.v1").expect("Module exists at path");
     // Deploy the module.
     let deployment = chain.module_deploy_v1(SIGNER, ALICE, module).expect("Deploy valid module");
-    let mut param = InitParameter{admins:Vec::new()};
-    param.admins.push(ALICE_ADDR);
-    param.admins.push(BOB_ADDR);
-    param.admins.push(MIKE_ADDR);
+    (chain, deployment.module_reference)
+}
 
-    // Initialize the contract.
-    let init = chain
-        .contract_init(SIGNER, ALICE, Energy::from(10_000), InitContractPayload {
-            amount:    Amount::zero(),
-            mod_ref:   deployment.module_reference,
-            init_name: OwnedContractName::new_unchecked("init_ccd_multisig".to_string()),
-            param:     OwnedParameter::from_serial(&param).unwrap(),
+/// Calls the receive function `entrypoint` of the contract as `sender`.
+fn update<P: Serial>(
+    chain: &mut Chain, init: &ContractInitSuccess, sender: AccountAddress,
+    entrypoint: &str, param: &P, amount: Amount,
+) -> Result<ContractInvokeSuccess, ContractInvokeError> {
+    chain.contract_update(SIGNER, sender, Address::Account(sender), Energy::from(100_000), UpdateContractPayload {
+        address:      init.contract_address,
+        amount,
+        receive_name: OwnedReceiveName::new_unchecked(format!("ccd_multisig.{}", entrypoint)),
+        message:      OwnedParameter::from_serial(param).unwrap()
+    })
+}
+
+/// Invokes the view `entrypoint` of the contract without updating the state.
+fn invoke<P: Serial>(
+    chain: &Chain, init: &ContractInitSuccess, entrypoint: &str, param: &P,
+) -> ContractInvokeSuccess {
+    chain.contract_invoke(ALICE, ALICE_ADDR, Energy::from(100_000), UpdateContractPayload {
+        address:      init.contract_address,
+        amount:       Amount::zero(),
+        receive_name: OwnedReceiveName::new_unchecked(format!("ccd_multisig.{}", entrypoint)),
+        message:      OwnedParameter::from_serial(param).unwrap()
+    })
+    .expect("Invoking view")
+}
+
+/// Proposes `kind` on behalf of `sender` and returns the proposal index.
+fn propose(
+    chain: &mut Chain, init: &ContractInitSuccess, sender: AccountAddress, kind: ProposalKind,
+) -> u32 {
+    let param = ProposeParameter{kind, deadline: None, auto_execute: false};
+    update(chain, init, sender, "propose", &param, Amount::zero())
+        .expect("Creating proposal")
+        .parse_return_value()
+        .expect("Proposal index")
+}
+
+/// Approves the proposal with every signer in `signers`, then executes it.
+fn approve_and_execute(
+    chain: &mut Chain, init: &ContractInitSuccess, index: u32, signers: &[AccountAddress],
+) {
+    for signer in signers {
+        update(chain, init, *signer, "approve", &ApproveParameter::new(index), Amount::zero())
+            .expect("Approve proposal");
+    }
+    update(chain, init, signers[0], "transfer", &ApproveParameter::new(index), Amount::zero())
+        .expect("Execute proposal");
+}
+
+/// Parses the events the contract logged during `update`.
+fn events(update: &ContractInvokeSuccess) -> Vec<Event> {
+    update
+        .events()
+        .flat_map(|(_, events)| events.iter().map(|event| event.parse().expect("Event")))
+        .collect()
+}
+
+/// Returns the proposal with the given index.
+fn view(chain: &Chain, init: &ContractInitSuccess, index: u32) -> Proposal {
+    invoke(chain, init, "view", &ApproveParameter::new(index))
+        .parse_return_value()
+        .expect("Proposal")
+}
+
+/// Sends `amount` CCD from `Alice` to the contract.
+fn fund(chain: &mut Chain, init: &ContractInitSuccess, amount: Amount) {
+    chain
+        .contract_update(SIGNER, ALICE, ALICE_ADDR, Energy::from(10_000), UpdateContractPayload {
+            address:      init.contract_address,
+            amount,
+            receive_name: OwnedReceiveName::new_unchecked("ccd_multisig.insert".to_string()),
+            message:      OwnedParameter::empty()
         })
-        .expect("Initializing contract");
+        .expect("Funding contract");
+}
 
-    (chain, init)
-}
\ No newline at end of file
+/// Proposes a transfer of `amount` to `receiver` on behalf of `sender`.
+fn create_tx(
+    chain: &mut Chain, init: &ContractInitSuccess, sender: AccountAddress,
+    receiver: AccountAddress, amount: Amount,
+) -> u32 {
+    let param = TxParameter{
+        amount,
+        receiver,
+        deadline: None,
+        auto_execute: false
+    };
+    update(chain, init, sender, "create_tx", &param, Amount::zero())
+        .expect("Creating proposal")
+        .parse_return_value()
+        .expect("Proposal index")
+}
+
+/// Builds a CIS-2 token amount; it shares its LEB128 encoding with `u64` amounts.
+fn tokens(amount: u64) -> TokenAmountU256 {
+    from_bytes(&to_bytes(&TokenAmountU64(amount))).expect("Token amount")
+}
+
+/// Replaces `account` with an account whose only key is derived from `secret`.
+fn create_account_with_key(chain: &mut Chain, account: AccountAddress, secret: [u8; 32]) {
+    let secret = ed25519_dalek::SecretKey::from_bytes(&secret).expect("Secret key");
+    let public = ed25519_dalek::PublicKey::from(&secret);
+    let keys = AccountAccessStructure{
+        keys: BTreeMap::from([(CredentialIndex{index: 0}, CredentialPublicKeys{
+            keys: BTreeMap::from([(KeyIndex(0), VerifyKey::Ed25519VerifyKey(public))]),
+            threshold: SignatureThreshold::ONE,
+        })]),
+        threshold: AccountThreshold::ONE,
+    };
+    let balance = AccountBalance::new(ACC_INITIAL_BALANCE, Amount::zero(), Amount::zero()).unwrap();
+    chain.create_account(Account::new_with_keys(account, balance, keys));
+}
+
+/// Signs `message` on behalf of `signer` with the key derived from `secret`.
+fn sign_permit(signer: AccountAddress, secret: [u8; 32], message: PermitMessage) -> Permit {
+    Permit{signer, signature: sign(signer, secret, &message), message}
+}
+
+/// Signs `message` on behalf of `signer` with the key derived from `secret`,
+/// hashing it the way wallets do.
+fn sign<M: Serial>(signer: AccountAddress, secret: [u8; 32], message: &M) -> concordium_std::AccountSignatures {
+    use ed25519_dalek::Signer;
+    let secret = ed25519_dalek::SecretKey::from_bytes(&secret).expect("Secret key");
+    let public = ed25519_dalek::PublicKey::from(&secret);
+    let keypair = ed25519_dalek::Keypair{secret, public};
+    let mut bytes = signer.0.to_vec();
+    bytes.extend_from_slice(&[0u8; 8]);
+    bytes.extend_from_slice(&to_bytes(message));
+    let signature = keypair.sign(&Sha256::digest(&bytes)).to_bytes();
+    let credential = concordium_std::CredentialSignatures{
+        sigs: BTreeMap::from([(0, concordium_std::Signature::Ed25519(concordium_std::SignatureEd25519(signature)))]),
+    };
+    concordium_std::AccountSignatures{sigs: BTreeMap::from([(0, credential)])}
+}
@@ -0,0 +1,41 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! A later version of the `ccd_multisig` contract whose state has a different
+//! layout. The integration tests upgrade to it to check that `Upgrade`
+//! proposals can migrate the state.
+use concordium_std::*;
+
+#[derive(Serialize, SchemaType)]
+pub struct State {
+    /// Layout version of the state, one past the version it was migrated from.
+    pub version: u32,
+    /// Layout version the state was migrated from.
+    pub migrated_from: u32,
+}
+
+#[init(contract = "ccd_multisig")]
+fn init(_ctx: &InitContext, _state_builder: &mut StateBuilder) -> InitResult<State> {
+    Ok(State { version: 1, migrated_from: 0 })
+}
+
+/// Replaces the state of the previous module with this module's layout. The
+/// previous state is not of this module's `State` type, so it is read from
+/// the root entry directly, where it starts with its version. Only the
+/// contract itself can call it.
+#[receive(contract = "ccd_multisig", name = "migrate", low_level)]
+fn migrate(ctx: &ReceiveContext, host: &mut LowLevelHost) -> ReceiveResult<()> {
+    ensure!(ctx.sender() == Address::Contract(ctx.self_address()));
+    let migrated_from = {
+        let mut root = host.state().lookup_entry(&[]).ok_or(ParseError {})?;
+        u32::deserial(&mut root)?
+    };
+    let version = migrated_from.checked_add(1).ok_or(Reject::default())?;
+    host.state_mut().write_root(&State { version, migrated_from });
+    Ok(())
+}
+
+/// Returns the layout version of the state and the version it was migrated from.
+#[receive(contract = "ccd_multisig", name = "get_version", return_value = "(u32, u32)")]
+fn get_version(_ctx: &ReceiveContext, host: &Host<State>) -> ReceiveResult<(u32, u32)> {
+    Ok((host.state().version, host.state().migrated_from))
+}